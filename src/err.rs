@@ -23,7 +23,7 @@ pub enum AppError {
     #[error("The version specified does not match the version currently strored")]
     IncompatibleVersions(String, String),
 
-    #[error("The version specified does not yet exist in the summary tables")]
+    #[error("The version specified does not yet exist in the ror schema or summary tables")]
     MissingVersion(String),
 
     #[error("couldn't read file {1:?}")]
@@ -41,6 +41,12 @@ pub enum AppError {
     #[error("Error when processing command line arguments: {0:?}")]
     ClapError(#[from] clap::Error),
 
+    #[error("Error when importing source data: {0:?} {1:?}")]
+    ImportError(String, String),
+
+    #[error("Error in language rules file: {0:?} {1:?}")]
+    RulesError(String, String),
+
     #[error("JSON processing error: {0:?}")]
     SerdeError(#[from] serde_json::Error),
 
@@ -83,7 +89,7 @@ pub fn report_error(e: AppError) -> () {
                     " INCOMPATIBLE VERSIONS"),
 
         AppError::MissingVersion(v_requested)  =>  print_error (
-                        format!("Data for the version specified ('{}') does not yet exist in the summary tables.", v_requested),
+                        format!("Data for the version specified ('{}') does not yet exist in the ror schema or summary tables.", v_requested),
                         " Run -r or -a with the specified version, to import the data and allow its processing and summarising.".to_string(), 
                         " MISSING VERSION"),
 
@@ -97,6 +103,10 @@ pub fn report_error(e: AppError) -> () {
 
         AppError::FileSystemError(p, d) => print_error (p, d, "FILE SYSTEM PROBLEM"),
         
+        AppError::ImportError(p, d) => print_error (p, d, "IMPORT ERROR"),
+
+        AppError::RulesError(p, d) => print_error (p, d, "LANGUAGE RULES ERROR"),
+
         AppError::SerdeError(e) => print_error ("Error occureed when parsing JSON file".to_string(), 
                     e.to_string(), "SERDE JSON ERROR"),
         
@@ -0,0 +1,145 @@
+/***************************************************************************
+ * Writes the summary data as csv files, for use in spreadsheets. For a 
+ * single version (-x) there is a totals file and one file for each of the
+ * summary dimensions (org status, type, country etc.). For all versions
+ * (-y) the same dimensions are pivoted, with a row for each category and
+ * a pair of columns (number and percentage) for each version, ordered by
+ * version date, so that trends across releases can be charted directly.
+ ***************************************************************************/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use csv::Writer;
+use crate::AppError;
+use super::smm_data::{AttCount, VersionAttCount, VersionSummary};
+
+
+pub fn write_version_totals(file_path: &Path, summaries: &[VersionSummary]) -> Result<(), AppError> {
+
+    let mut wtr = create_writer(file_path)?;
+    write_row(&mut wtr, file_path, ["data_version", "data_date", "num_orgs", "num_names",
+                                    "num_names_lc", "num_names_nolc", "names_stage"].map(String::from))?;
+    for vs in summaries {
+        write_row(&mut wtr, file_path, [vs.data_version.clone(), vs.data_date.clone(), vs.num_orgs.to_string(),
+                                        vs.num_names.to_string(), vs.num_names_lc.to_string(),
+                                        vs.num_names_nolc.to_string(), vs.names_stage.clone()])?;
+    }
+    finish(wtr, file_path)
+}
+
+
+pub fn write_att_counts(file_path: &Path, counts: &[AttCount]) -> Result<(), AppError> {
+
+    let mut wtr = create_writer(file_path)?;
+    write_row(&mut wtr, file_path, ["code", "name", "number", "pc"].map(String::from))?;
+    for c in counts {
+        write_row(&mut wtr, file_path, [c.att_code.clone().unwrap_or_default(), c.att_name.clone(),
+                                        c.number.to_string(), format!("{:.2}", c.pc)])?;
+    }
+    finish(wtr, file_path)
+}
+
+
+pub fn write_pivoted_counts(file_path: &Path, versions: &[String], counts: &[VersionAttCount]) -> Result<(), AppError> {
+
+    let mut wtr = create_writer(file_path)?;
+    let mut header = vec!["code".to_string(), "name".to_string()];
+    for v in versions {
+        header.push(format!("{} number", v));
+        header.push(format!("{} pc", v));
+    }
+    write_row(&mut wtr, file_path, header)?;
+    for row in pivot_counts(versions, counts) {
+        write_row(&mut wtr, file_path, row)?;
+    }
+    finish(wtr, file_path)
+}
+
+
+// Categories are ordered by their total number over all versions. Where
+// a category is absent from a version its number and percentage are left blank.
+
+fn pivot_counts(versions: &[String], counts: &[VersionAttCount]) -> Vec<Vec<String>> {
+
+    let mut categories: Vec<(Option<String>, String, i64)> = Vec::new();
+    let mut cells: HashMap<(Option<String>, &str), &AttCount> = HashMap::new();
+
+    for vc in counts {
+        let c = &vc.count;
+        match categories.iter_mut().find(|(code, _, _)| *code == c.att_code) {
+            Some((_, _, total)) => *total += c.number as i64,
+            None => categories.push((c.att_code.clone(), c.att_name.clone(), c.number as i64)),
+        }
+        cells.insert((c.att_code.clone(), vc.data_version.as_str()), c);
+    }
+    categories.sort_by_key(|(_, _, total)| std::cmp::Reverse(*total));
+
+    let mut rows = Vec::new();
+    for (code, name, _) in categories {
+        let mut row = vec![code.clone().unwrap_or_default(), name];
+        for v in versions {
+            match cells.get(&(code.clone(), v.as_str())) {
+                Some(c) => {
+                    row.push(c.number.to_string());
+                    row.push(format!("{:.2}", c.pc));
+                },
+                None => {
+                    row.push("".to_string());
+                    row.push("".to_string());
+                },
+            }
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+
+fn create_writer(file_path: &Path) -> Result<Writer<File>, AppError> {
+    Writer::from_path(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.to_owned()))
+}
+
+fn write_row<I: IntoIterator<Item = String>>(wtr: &mut Writer<File>, file_path: &Path, row: I) -> Result<(), AppError> {
+    wtr.write_record(row)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.to_owned()))
+}
+
+fn finish(mut wtr: Writer<File>, file_path: &Path) -> Result<(), AppError> {
+    wtr.flush()
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vatt(version: &str, code: &str, name: &str, number: i32, pc: f32) -> VersionAttCount {
+        VersionAttCount { data_version: version.to_string(),
+                          count: AttCount { att_code: Some(code.to_string()), att_name: name.to_string(), number, pc } }
+    }
+
+    #[test]
+    fn check_counts_are_pivoted_by_version() {
+        let versions = vec!["v1.58".to_string(), "v1.59".to_string()];
+        let counts = vec![vatt("v1.58", "FR", "France", 10, 50.0), vatt("v1.58", "DE", "Germany", 10, 50.0),
+                          vatt("v1.59", "DE", "Germany", 12, 48.0), vatt("v1.59", "FR", "France", 11, 44.0),
+                          vatt("v1.59", "IT", "Italy", 2, 8.0)];
+        let rows = pivot_counts(&versions, &counts);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], vec!["DE", "Germany", "10", "50.00", "12", "48.00"]);
+        assert_eq!(rows[1], vec!["FR", "France", "10", "50.00", "11", "44.00"]);
+        assert_eq!(rows[2], vec!["IT", "Italy", "", "", "2", "8.00"]);
+    }
+
+    #[test]
+    fn check_null_category_is_kept() {
+        let versions = vec!["v1.59".to_string()];
+        let counts = vec![VersionAttCount { data_version: "v1.59".to_string(),
+                          count: AttCount { att_code: None, att_name: "none".to_string(), number: 5, pc: 100.0 } }];
+        let rows = pivot_counts(&versions, &counts);
+        assert_eq!(rows[0], vec!["", "none", "5", "100.00"]);
+    }
+}
@@ -0,0 +1,78 @@
+/***************************************************************************
+ * The export module writes summaries of the data, taken from the smm
+ * tables, to the outputs folder. The text report (-t) and the csv files 
+ * (-x) are of a single version, either the current one or one specified,
+ * which can be any version that has been summarised. The full csv export
+ * (-y) covers all the versions in the smm tables.
+ ***************************************************************************/
+
+mod smm_data;
+mod text_report;
+mod csv_export;
+
+use log::info;
+use std::fs;
+use std::path::Path;
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+pub async fn export_text(data_version: &str, output_folder: &Path, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let vs = smm_data::fetch_version_summary(data_version, pool).await?
+        .ok_or_else(|| AppError::MissingVersion(data_version.to_string()))?;
+
+    let mut report = text_report::report_header(&vs);
+    for (att_type, heading, max_rows) in text_report::REPORT_SECTIONS {
+        let counts = smm_data::fetch_att_counts(data_version, att_type, pool).await?;
+        report += &text_report::report_section(heading, &counts, max_rows);
+    }
+
+    let file_path = output_folder.join(format!("ror summary {} {}.txt", vs.data_version, vs.data_date));
+    fs::write(&file_path, report)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.clone()))?;
+    info!("Summary of version {} written to {}", data_version, file_path.display());
+    Ok(())
+}
+
+
+pub async fn export_csv(data_version: &str, output_folder: &Path, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let vs = smm_data::fetch_version_summary(data_version, pool).await?
+        .ok_or_else(|| AppError::MissingVersion(data_version.to_string()))?;
+    let file_stem = format!("ror summary {} {}", vs.data_version, vs.data_date);
+
+    let file_path = output_folder.join(format!("{} totals.csv", file_stem));
+    csv_export::write_version_totals(&file_path, &[vs])?;
+
+    for att_type in smm_data::ATT_TYPES {
+        let counts = smm_data::fetch_att_counts(data_version, att_type, pool).await?;
+        let file_path = output_folder.join(format!("{} {}.csv", file_stem, att_type));
+        csv_export::write_att_counts(&file_path, &counts)?;
+    }
+    info!("Summary of version {} written to {} csv files in {}", data_version, 
+                    smm_data::ATT_TYPES.len() + 1, output_folder.display());
+    Ok(())
+}
+
+
+pub async fn export_full_csv(output_folder: &Path, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let summaries = smm_data::fetch_all_version_summaries(pool).await?;
+    if summaries.is_empty() {
+        return Err(AppError::MissingVersion("(any)".to_string()));
+    }
+    let versions: Vec<String> = summaries.iter().map(|vs| vs.data_version.clone()).collect();
+
+    let file_path = output_folder.join("ror summary all versions totals.csv");
+    csv_export::write_version_totals(&file_path, &summaries)?;
+
+    for att_type in smm_data::ATT_TYPES {
+        let counts = smm_data::fetch_all_att_counts(att_type, pool).await?;
+        let file_path = output_folder.join(format!("ror summary all versions {}.csv", att_type));
+        csv_export::write_pivoted_counts(&file_path, &versions, &counts)?;
+    }
+    info!("Summary of {} versions written to {} csv files in {}", versions.len(), 
+                    smm_data::ATT_TYPES.len() + 1, output_folder.display());
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+// The summary dimensions held in smm.attributes_summary.
+
+pub const ATT_TYPES: [&str; 9] = ["org_status", "org_type", "org_continent", "org_country",
+                                  "name_type", "name_lang", "name_script", "name_lang_source",
+                                  "name_nolc_country"];
+
+
+#[derive(sqlx::FromRow)]
+pub struct VersionSummary {
+    pub data_version: String,
+    pub data_date: String,
+    pub num_orgs: i32,
+    pub num_names: i32,
+    pub num_names_lc: i32,
+    pub num_names_nolc: i32,
+    pub names_stage: String,
+    pub summarised: String,
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct AttCount {
+    pub att_code: Option<String>,
+    pub att_name: String,
+    pub number: i32,
+    pub pc: f32,
+}
+
+
+#[derive(sqlx::FromRow)]
+pub struct VersionAttCount {
+    pub data_version: String,
+    #[sqlx(flatten)]
+    pub count: AttCount,
+}
+
+
+const VERSION_SUMMARY_SQL: &str = r#"select data_version, to_char(data_date, 'YYYY-MM-DD') as data_date, 
+            num_orgs, num_names, num_names_lc, num_names_nolc, names_stage,
+            to_char(summarised, 'YYYY-MM-DD HH24:MI') as summarised
+            from smm.version_summary"#;
+
+
+async fn smm_tables_exist(pool: &Pool<Postgres>) -> Result<bool, AppError> {
+
+    let sql = "select to_regclass('smm.version_summary') is not null";
+    sqlx::query_scalar(sql).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+pub async fn fetch_version_summary(data_version: &str, pool: &Pool<Postgres>) -> Result<Option<VersionSummary>, AppError> {
+
+    if !smm_tables_exist(pool).await? {
+        return Ok(None);
+    }
+
+    let sql = format!("{} where data_version = $1;", VERSION_SUMMARY_SQL);
+    sqlx::query_as(&sql).bind(data_version).fetch_optional(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn fetch_all_version_summaries(pool: &Pool<Postgres>) -> Result<Vec<VersionSummary>, AppError> {
+
+    if !smm_tables_exist(pool).await? {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!("{} order by data_date, data_version;", VERSION_SUMMARY_SQL);
+    sqlx::query_as(&sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))
+}
+
+
+pub async fn fetch_att_counts(data_version: &str, att_type: &str, pool: &Pool<Postgres>) -> Result<Vec<AttCount>, AppError> {
+
+    let sql = r#"select att_code, att_name, number, pc
+            from smm.attributes_summary
+            where data_version = $1
+            and att_type = $2
+            order by number desc, att_name;"#;
+    sqlx::query_as(sql).bind(data_version).bind(att_type).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+pub async fn fetch_all_att_counts(att_type: &str, pool: &Pool<Postgres>) -> Result<Vec<VersionAttCount>, AppError> {
+
+    let sql = r#"select data_version, att_code, att_name, number, pc
+            from smm.attributes_summary
+            where att_type = $1
+            order by data_version, number desc;"#;
+    sqlx::query_as(sql).bind(att_type).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
@@ -0,0 +1,115 @@
+/***************************************************************************
+ * Produces a plain text summary of a single version of the data, from the
+ * smm tables, in a form that can be attached to release notes. Long lists
+ * (countries and languages) are truncated to the most frequent entries.
+ ***************************************************************************/
+
+use super::smm_data::{AttCount, VersionSummary};
+
+
+// The sections of the report, as (att_type, heading, maximum rows) - 0 means all rows.
+
+pub const REPORT_SECTIONS: [(&str, &str, usize); 9] = [
+    ("org_status", "ORGANISATIONS BY STATUS", 0),
+    ("org_type", "ORGANISATIONS BY TYPE", 0),
+    ("org_continent", "ORGANISATIONS BY CONTINENT", 0),
+    ("org_country", "ORGANISATIONS BY COUNTRY (TOP 25)", 25),
+    ("name_type", "NAMES BY NAME TYPE", 0),
+    ("name_lang", "NAMES BY LANGUAGE (TOP 25)", 25),
+    ("name_script", "NAMES BY SCRIPT", 0),
+    ("name_lang_source", "NAMES BY LANGUAGE CODE SOURCE", 0),
+    ("name_nolc_country", "COUNTRIES WITH MOST NAMES WITHOUT A LANGUAGE CODE (TOP 20)", 20),
+];
+
+
+pub fn report_header(vs: &VersionSummary) -> String {
+
+    let stage = if vs.names_stage == "ext" { "after additional language coding" } else { "as imported" };
+    let mut s = String::new();
+    s += &format!("ROR DATA SUMMARY - VERSION {} ({})\n", vs.data_version, vs.data_date);
+    s += &format!("Summarised {}, with names {}\n\n", vs.summarised, stage);
+
+    s += "TOTALS\n";
+    s += &format!("    {:<40}{:>10}\n", "Organisations", vs.num_orgs);
+    s += &format!("    {:<40}{:>10}\n", "Names", vs.num_names);
+    s += &format!("    {:<40}{:>10}{:>10}\n", "Names with a language code", vs.num_names_lc, pc_of(vs.num_names_lc, vs.num_names));
+    s += &format!("    {:<40}{:>10}{:>10}\n", "Names without a language code", vs.num_names_nolc, pc_of(vs.num_names_nolc, vs.num_names));
+    s
+}
+
+
+pub fn report_section(heading: &str, counts: &[AttCount], max_rows: usize) -> String {
+
+    let mut s = format!("\n{}\n", heading);
+    let n = if max_rows == 0 { counts.len() } else { max_rows.min(counts.len()) };
+    for c in &counts[..n] {
+        let label = match &c.att_code {
+            Some(code) if *code != c.att_name => format!("{} ({})", c.att_name, code),
+            _ => c.att_name.clone(),
+        };
+        s += &format!("    {:<40}{:>10}{:>9.2}%\n", truncate(&label, 38), c.number, c.pc);
+    }
+    if counts.len() > n {
+        let others: i32 = counts[n..].iter().map(|c| c.number).sum();
+        s += &format!("    {:<40}{:>10}\n", format!("{} others", counts.len() - n), others);
+    }
+    s
+}
+
+
+fn pc_of(n: i32, total: i32) -> String {
+    if total == 0 {
+        return "".to_string();
+    }
+    format!("{:.2}%", 100.0 * n as f64 / total as f64)
+}
+
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        s.to_string()
+    }
+    else {
+        s.chars().take(max_chars - 3).collect::<String>() + "..."
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn att(code: &str, name: &str, number: i32, pc: f32) -> AttCount {
+        AttCount { att_code: Some(code.to_string()), att_name: name.to_string(), number, pc }
+    }
+
+    #[test]
+    fn check_report_header() {
+        let vs = VersionSummary { data_version: "v1.59".to_string(), data_date: "2025-01-23".to_string(),
+                                  num_orgs: 200, num_names: 400, num_names_lc: 100, num_names_nolc: 300,
+                                  names_stage: "src".to_string(), summarised: "2025-02-01 10:00".to_string() };
+        let s = report_header(&vs);
+        assert!(s.starts_with("ROR DATA SUMMARY - VERSION v1.59 (2025-01-23)\n"));
+        assert!(s.contains("with names as imported"));
+        assert!(s.contains("    Names without a language code                  300    75.00%\n"));
+    }
+
+    #[test]
+    fn check_report_section_is_truncated() {
+        let counts = vec![att("GB", "United Kingdom", 50, 50.0), att("FR", "France", 30, 30.0),
+                          att("DE", "Germany", 15, 15.0), att("IT", "Italy", 5, 5.0)];
+        let s = report_section("COUNTRIES", &counts, 2);
+        let lines: Vec<&str> = s.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[2], "    United Kingdom (GB)                             50    50.00%");
+        assert_eq!(lines[4], "    2 others                                        20");
+    }
+
+    #[test]
+    fn check_code_only_shown_when_different() {
+        let counts = vec![att("ror", "ror", 10, 100.0)];
+        let s = report_section("SOURCES", &counts, 0);
+        assert!(s.contains("    ror    "));
+        assert!(!s.contains("(ror)"));
+    }
+}
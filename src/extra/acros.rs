@@ -1,6 +1,45 @@
-//use sqlx::{Pool, Postgres};
-//use crate::AppError;
-//use log::info;
+/***************************************************************************
+ * Language codes for acronyms (name_type 10), which are not coded by the
+ * rules, location or model steps, as they carry little information about
+ * the language in themselves. An acronym is given the lang code of its
+ * org's other names if those names are all coded, with the same language
+ * (other than 'cm'), and the lang_source 'mono lang org'. The commented
+ * SQL below sketches the further step of matching acronyms against those
+ * derived from the initial letters of the org's other names.
+ ***************************************************************************/
+
+use sqlx::PgConnection;
+use crate::AppError;
+use log::info;
+use super::provenance::with_provenance;
+
+
+pub const MONO_LANG_STEP: &str = "mono lang org";
+
+
+pub async fn add_mono_lang_org_codes(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let update_sql = r#"update ext.names n
+            set lang_code = m.lang_code
+            from (select id, min(lang_code) as lang_code
+                  from ext.names
+                  where name_type <> 10
+                  and name_to_match is not null
+                  group by id
+                  having count(*) = count(lang_code)
+                  and count(distinct lang_code) = 1
+                  and min(lang_code) <> 'cm') m
+            where n.id = m.id
+            and n.name_type = 10
+            and n.lang_code is null"#;
+    let sql = with_provenance(update_sql, MONO_LANG_STEP, "mono_lang_org", "null");
+
+    let res = sqlx::raw_sql(&sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+    info!("{} acronyms given the single language of their org's other names", res.rows_affected());
+
+    Ok(())
+}
 
 
 /* 
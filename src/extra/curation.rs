@@ -0,0 +1,235 @@
+/***************************************************************************
+ * The curation store. The ext tables are dropped and rebuilt on every run
+ * of the additional processing (-q), so corrections made directly to them
+ * are lost. Corrections are therefore held in the 'cur' schema, whose
+ * tables are created if not present but never dropped, and are added to
+ * by hand (in SQL). There are four tables:
+ *   cur.org_types - the corrected set of org types for an org (all the
+ *     rows for an id replace the types supplied by ROR)
+ *   cur.added_names - extra name variants for an org, by default as
+ *     aliases, with an optional lang code
+ *   cur.lang_codes - forced lang codes, for an org id and exact name
+ *   cur.suppressed_names - names, for an org id, not to be used for matching
+ * The org type corrections and added names are applied as soon as the data
+ * is loaded, so that the name preparation and the language heuristics (e.g.
+ * the 'cm' coding of companies) work with the corrected data. The lang codes
+ * (including any given to added names) are applied, with the lang_source
+ * 'curated', after all the automatic steps for names, but before acronyms
+ * are given the language of their org's other names, so that they follow
+ * the curated codes. Suppressions are applied as soon as the names to
+ * match have been prepared - a suppressed name keeps its record but has
+ * its name_to_match set to null, so that it is not coded by the rules, the
+ * region or the language model, nor used to train the model or to code
+ * the org's acronyms.
+ * Entries that no longer match a current org or name are reported, as are
+ * any with unknown lang codes or org types, which are not applied.
+ ***************************************************************************/
+
+use sqlx::PgConnection;
+use log::{info, warn};
+use crate::AppError;
+use super::provenance::{remove_superseded, with_provenance};
+
+
+pub const CURATED_STEP: &str = "curated";
+
+
+async fn execute_sql(sql: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
+
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(res.rows_affected())
+}
+
+
+pub async fn create_curation_tables(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            create schema if not exists cur;
+            create table if not exists cur.org_types
+    (
+          id                varchar     not null
+        , org_type          int         not null
+        , notes             varchar     null
+        , added_on          timestamp   not null default now()
+        , primary key (id, org_type)
+    );
+            create table if not exists cur.added_names
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , name_type         int         not null default 7
+        , lang_code         varchar     null
+        , notes             varchar     null
+        , added_on          timestamp   not null default now()
+        , primary key (id, name)
+    );
+            create table if not exists cur.lang_codes
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , lang_code         varchar     not null
+        , notes             varchar     null
+        , added_on          timestamp   not null default now()
+        , primary key (id, name)
+    );
+            create table if not exists cur.suppressed_names
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , notes             varchar     null
+        , added_on          timestamp   not null default now()
+        , primary key (id, name)
+    );
+    SET client_min_messages TO NOTICE;"#;
+
+    execute_sql(sql, conn).await?;
+    Ok(())
+}
+
+
+pub async fn apply_org_type_corrections(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select org_type, count(*)
+            from cur.org_types
+            where org_type not in (select id from lup.ror_org_types)
+            group by org_type
+            order by org_type;"#;
+    let unknown: Vec<(i32, i64)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    for (org_type, num) in &unknown {
+        warn!("{} curated org types of {} not applied - not a known org type", num, org_type);
+    }
+
+    let sql = r#"delete from ext.type
+            where id in (select id from cur.org_types
+                         where org_type in (select id from lup.ror_org_types));"#;
+    execute_sql(sql, conn).await?;
+
+    let sql = r#"insert into ext.type (id, ror_name, org_type)
+            select c.id, o.ror_name, c.org_type
+            from cur.org_types c
+            inner join ext.orgs o on c.id = o.id
+            where c.org_type in (select id from lup.ror_org_types);"#;
+    let res = execute_sql(sql, conn).await?;
+    info!("{} curated org types applied", res);
+    Ok(())
+}
+
+
+// The names are added before the names to match are prepared, so the
+// name_to_match starts, as for the ROR names, as the lower-cased name.
+
+pub async fn add_curated_names(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"insert into ext.names (id, name, name_to_match, name_type, is_ror_name)
+            select c.id, c.name, lower(c.name), c.name_type, false
+            from cur.added_names c
+            inner join ext.orgs o on c.id = o.id
+            where not exists (select 1 from ext.names n
+                              where n.id = c.id and n.name = c.name);"#;
+    let res = execute_sql(sql, conn).await?;
+    info!("{} curated names added", res);
+    Ok(())
+}
+
+
+pub async fn apply_curated_lang_codes(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select lang_code, count(*)
+            from (select lang_code from cur.lang_codes
+                  union all
+                  select lang_code from cur.added_names where lang_code is not null) c
+            where lang_code not in (select code from lup.lang_codes)
+            group by lang_code
+            order by lang_code;"#;
+    let unknown: Vec<(String, i64)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    for (lang_code, num) in &unknown {
+        warn!("{} curated codes of '{}' not applied - not a known language code", num, lang_code);
+    }
+
+    // The codes of the added names are applied first, so that an entry in
+    // cur.lang_codes for the same name takes precedence.
+
+    let mut num_applied = force_lang_codes("cur.added_names", conn).await?;
+    num_applied += force_lang_codes("cur.lang_codes", conn).await?;
+    info!("{} curated language codes applied", num_applied);
+    Ok(())
+}
+
+
+async fn force_lang_codes(table: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
+
+    let matches = format!(r#"from {} c
+            where n.id = c.id and n.name = c.name
+            and c.lang_code in (select code from lup.lang_codes)"#, table);
+
+    remove_superseded(&format!("exists (select 1 {})", matches), conn).await?;
+
+    let update_sql = format!(r#"update ext.names n
+            set lang_code = c.lang_code,
+            lang_source = '{}'
+            {}"#, CURATED_STEP, matches);
+    let sql = with_provenance(&update_sql, CURATED_STEP, table, "c.notes");
+    let res = sqlx::raw_sql(&sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+    Ok(res.rows_affected())
+}
+
+
+pub async fn suppress_curated_names(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"update ext.names n
+            set name_to_match = null
+            from cur.suppressed_names c
+            where n.id = c.id and n.name = c.name;"#;
+    let res = execute_sql(sql, conn).await?;
+    info!("{} curated names suppressed from matching", res);
+    Ok(())
+}
+
+
+pub async fn report_stale_curation(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select 'cur.org_types', c.id, c.org_type::varchar
+            from cur.org_types c
+            where not exists (select 1 from ext.orgs o where o.id = c.id)
+            union all
+            select 'cur.added_names', c.id, c.name
+            from cur.added_names c
+            where not exists (select 1 from ext.orgs o where o.id = c.id)
+            union all
+            select 'cur.lang_codes', c.id, c.name
+            from cur.lang_codes c
+            where not exists (select 1 from ext.names n where n.id = c.id and n.name = c.name)
+            union all
+            select 'cur.suppressed_names', c.id, c.name
+            from cur.suppressed_names c
+            where not exists (select 1 from ext.names n where n.id = c.id and n.name = c.name)
+            order by 1, 2, 3;"#;
+    let stale: Vec<(String, String, String)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !stale.is_empty() {
+        warn!("{} curation entries no longer match a current org or name:", stale.len());
+        for (table, id, value) in stale {
+            warn!("    {}: {} '{}'", table, id, value);
+        }
+    }
+
+    // Added names that ROR now supplies itself are no longer needed.
+
+    let sql = r#"select c.id, c.name
+            from cur.added_names c
+            where exists (select 1 from src.names s where s.id = c.id and s.value = c.name)
+            order by c.id, c.name;"#;
+    let redundant: Vec<(String, String)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !redundant.is_empty() {
+        warn!("{} curated added names are now in the ROR data:", redundant.len());
+        for (id, name) in redundant {
+            warn!("    {} '{}'", id, name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+/***************************************************************************
+ * Evaluation of the heuristic language coding (-e). The lang codes supplied
+ * by ROR are treated as the 'gold' data. They are copied to ext.lang_gold
+ * and removed from ext.names, and the script ('script_auto'), keyword rule
+ * ('lex_auto'), location ('region_auto'), language model ('model_auto') 
+ * and acronym ('mono lang org') heuristics are then run over all the names,
+ * as in the normal additional processing. (The 'cm' coding of commercial orgs
+ * is not a language heuristic, and is not included, nor are the manual and
+ * curated codes.) The codes predicted for the gold names, and the step that
+ * predicted them, are compared with the ROR codes, and two csv files are
+ * written to the outputs folder:
+ * precision and recall for each language, overall and for each step, and
+ * a confusion matrix of ROR codes against predicted codes. The whole
+ * evaluation takes place in a transaction that is rolled back at the end.
+ ***************************************************************************/
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use sqlx::PgConnection;
+use log::info;
+use crate::AppError;
+
+pub const NOT_CODED: &str = "none";
+pub const ALL: &str = "all";
+
+
+pub struct EvalRow {
+    pub gold: String,
+    pub predicted: Option<String>,
+    pub step: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Metrics {
+    pub step: String,
+    pub lang_code: String,
+    pub gold: usize,
+    pub predicted: usize,
+    pub correct: usize,
+}
+
+impl Metrics {
+    fn precision(&self) -> Option<f64> {
+        ratio(self.correct, self.predicted)
+    }
+    fn recall(&self) -> Option<f64> {
+        ratio(self.correct, self.gold)
+    }
+}
+
+fn ratio(n: usize, d: usize) -> Option<f64> {
+    if d == 0 { None } else { Some(100.0 * n as f64 / d as f64) }
+}
+
+fn pc_text(v: Option<f64>) -> String {
+    v.map(|v| format!("{:.2}", v)).unwrap_or_default()
+}
+
+
+pub async fn hide_gold_codes(conn: &mut PgConnection) -> Result<u64, AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_gold;
+            create table ext.lang_gold as
+            select id, name, name_type, lang_code
+            from ext.names
+            where lang_source = 'ror';
+            SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = r#"update ext.names
+            set lang_code = null, lang_source = null
+            where lang_source = 'ror';"#;
+
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(res.rows_affected())
+}
+
+
+pub async fn fetch_eval_rows(conn: &mut PgConnection) -> Result<Vec<EvalRow>, AppError> {
+
+    let sql = r#"select g.lang_code, p.lang_code, p.step
+            from ext.lang_gold g
+            left join ext.lang_provenance p
+            on g.id = p.id and g.name = p.name
+            and g.name_type is not distinct from p.name_type;"#;
+
+    let rows: Vec<(String, Option<String>, Option<String>)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(rows.into_iter().map(|(gold, predicted, step)| EvalRow { gold, predicted, step }).collect())
+}
+
+
+// Metrics for each language, and for all languages together, both for all
+// the steps and for each step separately. Recall is always relative to all
+// the gold names of the language, so the recall of the separate steps shows
+// the contribution of each.
+
+pub fn calculate_metrics(rows: &[EvalRow]) -> Vec<Metrics> {
+
+    let langs: BTreeSet<&str> = rows.iter().map(|r| r.gold.as_str())
+        .chain(rows.iter().filter_map(|r| r.predicted.as_deref()))
+        .collect();
+    let steps: BTreeSet<&str> = rows.iter().filter_map(|r| r.step.as_deref()).collect();
+
+    let mut metrics = Vec::new();
+    for step in std::iter::once(ALL).chain(steps) {
+        let in_step = |r: &EvalRow| step == ALL || r.step.as_deref() == Some(step);
+        for lang in std::iter::once(ALL).chain(langs.iter().copied()) {
+            let is_lang = |code: &str| lang == ALL || code == lang;
+            metrics.push(Metrics {
+                step: step.to_string(),
+                lang_code: lang.to_string(),
+                gold: rows.iter().filter(|r| is_lang(&r.gold)).count(),
+                predicted: rows.iter().filter(|r| in_step(r) && r.predicted.as_deref().is_some_and(is_lang)).count(),
+                correct: rows.iter().filter(|r| in_step(r) && is_lang(&r.gold)
+                                             && r.predicted.as_deref() == Some(r.gold.as_str())).count(),
+            });
+        }
+    }
+    metrics
+}
+
+
+pub fn confusion_matrix(rows: &[EvalRow]) -> (Vec<String>, Vec<Vec<String>>) {
+
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    let mut gold_langs = BTreeSet::new();
+    let mut predicted_langs = BTreeSet::new();
+    for r in rows {
+        let predicted = r.predicted.as_deref().unwrap_or(NOT_CODED);
+        *counts.entry((r.gold.as_str(), predicted)).or_insert(0) += 1;
+        gold_langs.insert(r.gold.as_str());
+        if predicted != NOT_CODED {
+            predicted_langs.insert(predicted);
+        }
+    }
+    let columns: Vec<&str> = predicted_langs.into_iter().chain(std::iter::once(NOT_CODED)).collect();
+
+    let mut header = vec!["ror \\ predicted".to_string()];
+    header.extend(columns.iter().map(|c| c.to_string()));
+    let mut matrix = Vec::new();
+    for gold in gold_langs {
+        let mut row = vec![gold.to_string()];
+        row.extend(columns.iter().map(|c| counts.get(&(gold, *c)).unwrap_or(&0).to_string()));
+        matrix.push(row);
+    }
+    (header, matrix)
+}
+
+
+pub fn write_evaluation(rows: &[EvalRow], file_stem: &str, output_folder: &Path) -> Result<(), AppError> {
+
+    let file_path = output_folder.join(format!("{} metrics.csv", file_stem));
+    let mut wtr = csv::Writer::from_path(&file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+    wtr.write_record(["step", "lang_code", "ror_names", "predicted", "correct", "precision", "recall"])
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+    for m in calculate_metrics(rows) {
+        wtr.write_record([m.step.clone(), m.lang_code.clone(), m.gold.to_string(), m.predicted.to_string(),
+                          m.correct.to_string(), pc_text(m.precision()), pc_text(m.recall())])
+            .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+        if m.lang_code == ALL {
+            info!("Evaluation, step {}: {} of {} predictions correct, precision {}%, recall {}%", m.step,
+                  m.correct, m.predicted, pc_text(m.precision()), pc_text(m.recall()));
+        }
+    }
+    wtr.flush().map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.clone()))?;
+
+    let file_path = output_folder.join(format!("{} confusion matrix.csv", file_stem));
+    let mut wtr = csv::Writer::from_path(&file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+    let (header, matrix) = confusion_matrix(rows);
+    wtr.write_record(header)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+    for row in matrix {
+        wtr.write_record(row)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.clone()))?;
+    }
+    wtr.flush().map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.clone()))?;
+
+    info!("Evaluation of {} ror coded names written to {}", rows.len(), output_folder.display());
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(gold: &str, predicted: Option<&str>, step: Option<&str>) -> EvalRow {
+        EvalRow { gold: gold.to_string(), predicted: predicted.map(String::from), step: step.map(String::from) }
+    }
+
+    fn test_rows() -> Vec<EvalRow> {
+        vec![row("ja", Some("ja"), Some("lex_auto")), row("ja", Some("ja"), Some("script_auto")),
+             row("ja", Some("zh"), Some("script_auto")), row("ja", None, None),
+             row("en", Some("en"), Some("lex_auto")), row("de", Some("en"), Some("lex_auto"))]
+    }
+
+    fn find<'a>(metrics: &'a [Metrics], step: &str, lang: &str) -> &'a Metrics {
+        metrics.iter().find(|m| m.step == step && m.lang_code == lang).unwrap()
+    }
+
+    #[test]
+    fn check_metrics_by_language_and_step() {
+        let metrics = calculate_metrics(&test_rows());
+
+        let ja = find(&metrics, ALL, "ja");
+        assert_eq!((ja.gold, ja.predicted, ja.correct), (4, 2, 2));
+        assert_eq!(ja.precision(), Some(100.0));
+        assert_eq!(ja.recall(), Some(50.0));
+
+        let en = find(&metrics, ALL, "en");
+        assert_eq!((en.gold, en.predicted, en.correct), (1, 2, 1));
+        assert_eq!(en.precision(), Some(50.0));
+
+        let zh = find(&metrics, ALL, "zh");
+        assert_eq!((zh.gold, zh.predicted, zh.correct), (0, 1, 0));
+        assert_eq!(zh.recall(), None);
+
+        let lex = find(&metrics, "lex_auto", ALL);
+        assert_eq!((lex.gold, lex.predicted, lex.correct), (6, 3, 2));
+        let script_ja = find(&metrics, "script_auto", "ja");
+        assert_eq!((script_ja.gold, script_ja.predicted, script_ja.correct), (4, 1, 1));
+        assert_eq!(script_ja.recall(), Some(25.0));
+    }
+
+    #[test]
+    fn check_confusion_matrix() {
+        let (header, matrix) = confusion_matrix(&test_rows());
+        assert_eq!(header, vec!["ror \\ predicted", "en", "ja", "zh", "none"]);
+        assert_eq!(matrix, vec![vec!["de", "1", "0", "0", "0"],
+                                vec!["en", "1", "0", "0", "0"],
+                                vec!["ja", "0", "2", "1", "1"]]);
+    }
+}
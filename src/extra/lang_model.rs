@@ -0,0 +1,327 @@
+/***************************************************************************
+ * An offline statistical language identifier, for the names still without
+ * a lang code after the script, keyword and location steps. A character
+ * trigram model (naive Bayes, with add-one smoothing) is trained in memory
+ * from the names whose lang codes come from trusted sources (by default
+ * 'ror' and 'manual' - heuristic sources can be added, but the model will
+ * then repeat their errors). It then suggests a language for each
+ * remaining name other than acronyms, with a confidence that is the
+ * posterior probability of that language. Because that probability is
+ * relative to the languages in the model, it can be high for a name in a
+ * language the model has never seen, so the coverage - the proportion of
+ * the name's trigrams found in the training names of the suggested
+ * language - is also calculated. All the suggestions are stored in
+ * ext.lang_model_suggestions, but only those with a confidence and a
+ * coverage at or above the thresholds are applied, with 'model_auto' as
+ * the lang_source. The thresholds, the training sources and the minimum
+ * number of training names needed for a language to be included are set in
+ * the [lang_model] section of lang_rules.toml.
+ ***************************************************************************/
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use serde::Deserialize;
+use sqlx::PgConnection;
+use log::{info, warn};
+use crate::AppError;
+use crate::import::copy_writer::{copy_rows_on_conn, CopyRows, CopyWriter};
+use super::lang_rules::get_rules_file;
+use super::provenance::with_provenance;
+
+
+pub const MODEL_STEP: &str = "model_auto";
+const MODEL_RULE: &str = "ngram_model";
+const NGRAM_SIZE: usize = 3;
+
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct ModelSettings {
+    pub threshold: f64,
+    pub min_coverage: f64,
+    pub min_training_names: usize,
+    pub training_sources: Vec<String>,
+}
+
+impl Default for ModelSettings {
+    fn default() -> Self {
+        ModelSettings {
+            threshold: 0.95,
+            min_coverage: 0.6,
+            min_training_names: 50,
+            training_sources: vec!["ror".to_string(), "manual".to_string()],
+        }
+    }
+}
+
+
+struct LangProfile {
+    lang_code: String,
+    num_names: usize,
+    log_prior: f64,
+    log_denominator: f64,
+    counts: HashMap<String, u32>,
+}
+
+pub struct LangModel {
+    profiles: Vec<LangProfile>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+    pub lang_code: String,
+    pub confidence: f64,
+    pub coverage: f64,
+}
+
+
+// Only letters are used, lower-cased, with each word separated by a single
+// space and the whole padded with spaces, so that word starts and ends
+// form trigrams of their own. Digits (and punctuation) carry no language
+// information and are dropped.
+
+pub fn name_ngrams(text: &str) -> Vec<String> {
+
+    let mut cleaned = vec![' '];
+    for word in text.split(|c: char| !c.is_alphabetic()).filter(|w| !w.is_empty()) {
+        cleaned.extend(word.to_lowercase().chars());
+        cleaned.push(' ');
+    }
+    if cleaned.len() < NGRAM_SIZE {
+        return Vec::new();
+    }
+    cleaned.windows(NGRAM_SIZE).map(|w| w.iter().collect()).collect()
+}
+
+
+impl LangModel {
+
+    pub fn train(samples: &[(String, String)], min_names: usize) -> Self {
+
+        let mut by_lang: BTreeMap<&str, (usize, HashMap<String, u32>)> = BTreeMap::new();
+        for (lang_code, text) in samples {
+            let ngrams = name_ngrams(text);
+            if ngrams.is_empty() {
+                continue;
+            }
+            let (num_names, counts) = by_lang.entry(lang_code.as_str()).or_default();
+            *num_names += 1;
+            for ngram in ngrams {
+                *counts.entry(ngram).or_insert(0) += 1;
+            }
+        }
+        by_lang.retain(|_, (num_names, _)| *num_names >= min_names);
+
+        let vocabulary: HashSet<&String> = by_lang.values().flat_map(|(_, counts)| counts.keys()).collect();
+        let vocab_size = vocabulary.len() as f64;
+        let total_names: usize = by_lang.values().map(|(num_names, _)| num_names).sum();
+
+        let profiles = by_lang.into_iter().map(|(lang_code, (num_names, counts))| {
+            let total_ngrams: u32 = counts.values().sum();
+            LangProfile {
+                lang_code: lang_code.to_string(),
+                num_names,
+                log_prior: (num_names as f64 / total_names as f64).ln(),
+                log_denominator: (total_ngrams as f64 + vocab_size).ln(),
+                counts,
+            }
+        }).collect();
+        LangModel { profiles }
+    }
+
+    pub fn num_langs(&self) -> usize {
+        self.profiles.len()
+    }
+
+    pub fn classify(&self, text: &str) -> Option<Suggestion> {
+
+        let ngrams = name_ngrams(text);
+        if ngrams.is_empty() || self.profiles.is_empty() {
+            return None;
+        }
+
+        let scores: Vec<f64> = self.profiles.iter().map(|p| {
+            p.log_prior + ngrams.iter()
+                .map(|g| (*p.counts.get(g).unwrap_or(&0) as f64 + 1.0).ln() - p.log_denominator)
+                .sum::<f64>()
+        }).collect();
+
+        let (best, best_score) = scores.iter().enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+        let sum_exp: f64 = scores.iter().map(|s| (s - best_score).exp()).sum();
+        let profile = &self.profiles[best];
+        let num_seen = ngrams.iter().filter(|g| profile.counts.contains_key(*g)).count();
+        Some(Suggestion {
+            lang_code: profile.lang_code.clone(),
+            confidence: 1.0 / sum_exp,
+            coverage: num_seen as f64 / ngrams.len() as f64,
+        })
+    }
+}
+
+
+#[derive(Default)]
+struct SuggestionVecs {
+    ids: Vec<String>,
+    names: Vec<String>,
+    name_types: Vec<Option<i32>>,
+    lang_codes: Vec<String>,
+    confidences: Vec<f64>,
+    coverages: Vec<f64>,
+}
+
+impl CopyRows for SuggestionVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ext.lang_model_suggestions (id, name, name_type, lang_code, confidence, coverage) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.names[i]).field(&self.name_types[i])
+             .field(&self.lang_codes[i]).field(&self.confidences[i]).field(&self.coverages[i]);
+            w.end_row();
+        }
+    }
+}
+
+
+pub async fn apply_model_codes(data_folder: &Path, write_defaults: bool, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let settings = get_rules_file(data_folder, write_defaults)?.lang_model;
+
+    let sql = r#"select lang_code, name_to_match
+            from ext.names
+            where lang_source = any($1)
+            and name_type <> 10
+            and name_to_match is not null;"#;
+    let samples: Vec<(String, String)> = sqlx::query_as(sql).bind(&settings.training_sources)
+        .fetch_all(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let model = LangModel::train(&samples, settings.min_training_names);
+    if model.num_langs() < 2 {
+        warn!("Language model not applied - fewer than two languages have {} or more training names",
+              settings.min_training_names);
+        return Ok(());
+    }
+    let langs: Vec<String> = model.profiles.iter().map(|p| format!("{} ({})", p.lang_code, p.num_names)).collect();
+    info!("Language model trained on {} languages: {}", model.num_langs(), langs.join(", "));
+
+    let sql = r#"select id, name, name_type, name_to_match
+            from ext.names
+            where lang_code is null
+            and name_type <> 10
+            and name_to_match is not null;"#;
+    let uncoded: Vec<(String, String, Option<i32>, String)> = sqlx::query_as(sql)
+        .fetch_all(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut sv = SuggestionVecs::default();
+    let mut lang_counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for (id, name, name_type, name_to_match) in uncoded {
+        if let Some(s) = model.classify(&name_to_match) {
+            let (suggested, above_threshold) = lang_counts.entry(s.lang_code.clone()).or_default();
+            *suggested += 1;
+            if s.confidence >= settings.threshold && s.coverage >= settings.min_coverage {
+                *above_threshold += 1;
+            }
+            sv.ids.push(id);
+            sv.names.push(name);
+            sv.name_types.push(name_type);
+            sv.lang_codes.push(s.lang_code);
+            sv.confidences.push(s.confidence);
+            sv.coverages.push(s.coverage);
+        }
+    }
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_model_suggestions;
+            create table ext.lang_model_suggestions
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , name_type         int         null
+        , lang_code         varchar     not null
+        , confidence        float8      not null
+        , coverage          float8      not null
+    );
+    SET client_min_messages TO NOTICE;"#;
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    copy_rows_on_conn(&sv, conn).await?;
+
+    let sql = "create index lang_model_suggestions_idx on ext.lang_model_suggestions(id, name);";
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    for (lang_code, (suggested, above_threshold)) in &lang_counts {
+        info!("{} names suggested as '{}' by the language model, {} above the thresholds",
+              suggested, lang_code, above_threshold);
+    }
+
+    let update_sql = r#"update ext.names n
+            set lang_code = s.lang_code
+            from ext.lang_model_suggestions s
+            where n.id = s.id and n.name = s.name
+            and n.name_type is not distinct from s.name_type
+            and n.lang_code is null
+            and s.confidence >= $1
+            and s.coverage >= $2"#;
+    let sql = with_provenance(update_sql, MODEL_STEP, MODEL_RULE, "round(s.confidence::numeric, 4)::varchar");
+    let res = sqlx::query(&sql).bind(settings.threshold).bind(settings.min_coverage)
+        .execute(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+    info!("{} language codes added by the language model, of {} suggestions", res.rows_affected(), sv.ids.len());
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<(String, String)> {
+        let mut samples = Vec::new();
+        for name in ["universität hamburg", "hochschule für musik", "deutsches zentrum für luftfahrt",
+                     "technische universität münchen", "fraunhofer gesellschaft"] {
+            samples.push(("de".to_string(), name.to_string()));
+        }
+        for name in ["universidad de granada", "hospital universitario la paz", "instituto de salud carlos",
+                     "universidad politécnica de madrid", "consejo superior de investigaciones científicas"] {
+            samples.push(("es".to_string(), name.to_string()));
+        }
+        samples.push(("fr".to_string(), "université de lille".to_string()));
+        samples
+    }
+
+    #[test]
+    fn check_name_ngrams() {
+        assert_eq!(name_ngrams("Ab-c 12"), vec![" ab", "ab ", "b c", " c "]);
+        assert_eq!(name_ngrams("x"), vec![" x "]);
+        assert!(name_ngrams("1234 ()").is_empty());
+    }
+
+    #[test]
+    fn check_model_classifies_by_trained_languages() {
+        let model = LangModel::train(&samples(), 2);
+        assert_eq!(model.num_langs(), 2);      // fr has too few names
+
+        let de = model.classify("universität stuttgart").unwrap();
+        assert_eq!(de.lang_code, "de");
+        let es = model.classify("universidad de sevilla").unwrap();
+        assert_eq!(es.lang_code, "es");
+        assert!(es.confidence > 0.5 && es.confidence <= 1.0);
+        assert!(es.coverage > 0.5);
+        assert_eq!(model.classify("12345"), None);
+
+        let unseen = model.classify("kyoto daigaku").unwrap();
+        assert!(unseen.coverage < 0.5);
+    }
+
+    #[test]
+    fn check_settings_default_when_omitted() {
+        let settings: ModelSettings = toml::from_str("threshold = 0.8").unwrap();
+        assert_eq!(settings.threshold, 0.8);
+        assert_eq!(settings.min_coverage, 0.6);
+        assert_eq!(settings.min_training_names, 50);
+        assert_eq!(settings.training_sources, vec!["ror", "manual"]);
+    }
+}
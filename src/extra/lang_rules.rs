@@ -0,0 +1,442 @@
+/***************************************************************************
+ * The language rules engine. Rules that derive a name's language from key
+ * words or word parts, optionally restricted to orgs in particular
+ * countries, are held in a toml file (lang_rules.toml) in the data folder,
+ * so that they can be maintained without recompiling the program. If that
+ * file does not exist the default rules, compiled into the program, are
+ * first written to it (except in a dry run, when they are simply used).
+ * The defaults carry a version number, and a warning is given if the file
+ * in the data folder is from an older version, as it will not include
+ * later changes to the default rules. Before any rule is applied the lang
+ * codes of all of them are checked against lup.lang_codes. Each rule is
+ * applied as a single update statement, with its patterns and country
+ * lists passed as array parameters, in descending order of priority, to
+ * names that do not yet have a lang code. The rule's name acts as its id
+ * in the provenance table, along with the first of its include patterns
+ * that the name matched. Before the rules are applied the names matching
+ * each of them are recorded, so that names matching rules for more than
+ * one language can be reported (see rule_conflicts.rs). The same file
+ * holds the settings for the language model that is applied after the
+ * rules (see lang_model.rs).
+ ***************************************************************************/
+
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use sqlx::{PgConnection, Postgres};
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use std::collections::HashSet;
+use log::{info, warn};
+use crate::AppError;
+use super::provenance::with_provenance;
+use super::lang_model::ModelSettings;
+use super::rule_conflicts;
+
+
+pub const RULES_FILE_NAME: &str = "lang_rules.toml";
+const DEFAULT_RULES: &str = include_str!("lang_rules.toml");
+const DEFAULT_RULES_VERSION: u32 = 4;     // must match the version in lang_rules.toml
+
+
+#[derive(Debug, Deserialize)]
+pub struct LangRulesFile {
+    #[serde(default)]
+    pub version: u32,
+    pub rules: Vec<LangRule>,
+    #[serde(default)]
+    pub lang_model: ModelSettings,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    #[default]
+    NameToMatch,
+    Name,
+}
+
+impl MatchField {
+    fn column(&self) -> &'static str {
+        match self {
+            MatchField::NameToMatch => "name_to_match",
+            MatchField::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LangRule {
+    pub name: String,
+    pub lang_code: String,
+    pub priority: i32,
+    #[serde(default)]
+    pub countries: Vec<String>,
+    #[serde(default)]
+    pub exclude_countries: Vec<String>,
+    #[serde(default)]
+    pub exclude_subdivisions: Vec<String>,
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub skip_name_types: Vec<i32>,
+    #[serde(default)]
+    pub match_on: MatchField,
+}
+
+
+pub fn parse_rules_file(rules_string: &str, source: &str) -> Result<LangRulesFile, AppError> {
+
+    let mut rules_file: LangRulesFile = toml::from_str(rules_string)
+        .map_err(|e| AppError::RulesError(format!("Unable to parse language rules in {}.", source), e.to_string()))?;
+    let rules = &mut rules_file.rules;
+
+    for (i, rule) in rules.iter().enumerate() {
+        if rules[..i].iter().any(|r| r.name == rule.name) {
+            return Err(AppError::RulesError(format!("Language rule name '{}' in {} is used more than once.", rule.name, source),
+                                            "Rule names identify rules in the provenance table, and must be unique.".to_string()));
+        }
+        if rule.lang_code.trim().is_empty() {
+            return Err(AppError::RulesError(format!("Language rule '{}' in {} has no lang_code.", rule.name, source),
+                                            "Every rule must specify the language code to apply.".to_string()));
+        }
+        if rule.include.is_empty() {
+            return Err(AppError::RulesError(format!("Language rule '{}' in {} has no include patterns.", rule.name, source),
+                                            "Every rule must have at least one include pattern.".to_string()));
+        }
+    }
+
+    rules.sort_by_key(|r| std::cmp::Reverse(r.priority));   // stable, so file order retained within a priority
+
+    let model = &rules_file.lang_model;
+    for (setting, value) in [("threshold", model.threshold), ("min_coverage", model.min_coverage)] {
+        if !(value > 0.0 && value <= 1.0) {
+            return Err(AppError::RulesError(format!("Language model {} {} in {} is out of range.", setting, value, source),
+                                            "The value is a proportion, and must be greater than 0 and no more than 1.".to_string()));
+        }
+    }
+    Ok(rules_file)
+}
+
+
+pub fn get_rules_file(data_folder: &Path, write_defaults: bool) -> Result<LangRulesFile, AppError> {
+
+    let rules_path = data_folder.join(RULES_FILE_NAME);
+    if !rules_path.exists() {
+        if !write_defaults {
+            info!("No {} in the data folder - the default language rules are used, but not written", RULES_FILE_NAME);
+            return parse_rules_file(DEFAULT_RULES, "default rules");
+        }
+        fs::write(&rules_path, DEFAULT_RULES)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, rules_path.clone()))?;
+        info!("Default language rules written to {}", rules_path.display());
+    }
+
+    let rules_string = fs::read_to_string(&rules_path)
+        .map_err(|e| AppError::IoReadErrorWithPath(e, rules_path.clone()))?;
+    let rules_file = parse_rules_file(&rules_string, &rules_path.display().to_string())?;
+
+    if rules_file.version < DEFAULT_RULES_VERSION {
+        warn!("{} is version {} of the language rules, but the program's default rules are version {}. \
+               Changes to the defaults are not in the file - rename or delete it to have the current \
+               defaults written in its place, or merge them in by hand.",
+              rules_path.display(), rules_file.version, DEFAULT_RULES_VERSION);
+    }
+    Ok(rules_file)
+}
+
+
+// A mistyped lang code would otherwise be written straight into ext.names.
+
+pub async fn check_lang_codes(rules: &[LangRule], conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = "select code from lup.lang_codes;";
+    let codes: HashSet<String> = sqlx::query_scalar(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?
+        .into_iter().collect();
+
+    for rule in rules {
+        if !codes.contains(&rule.lang_code) {
+            return Err(AppError::RulesError(format!("Language rule '{}' has the lang_code '{}', which is not in lup.lang_codes.", 
+                                                    rule.name, rule.lang_code),
+                                            "Correct the code in the rules file - no rules have been applied.".to_string()));
+        }
+    }
+    Ok(())
+}
+
+
+// The equivalent of sql 'like any', for patterns (such as the exclude lists
+// of the rules) used outside the database. Only '%' and '_' are handled -
+// backslash escapes, not used in the rules, are not.
+
+pub fn like_any(value: &str, patterns: &[String]) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    patterns.iter().any(|p| is_like(&value, &p.chars().collect::<Vec<char>>()))
+}
+
+fn is_like(value: &[char], pattern: &[char]) -> bool {
+    match pattern.split_first() {
+        None => value.is_empty(),
+        Some(('%', rest)) => (0..=value.len()).any(|i| is_like(&value[i..], rest)),
+        Some((p, rest)) => match value.split_first() {
+            Some((v, v_rest)) => (*p == '_' || p == v) && is_like(v_rest, rest),
+            None => false,
+        },
+    }
+}
+
+
+// Empty arrays make the corresponding condition always true, so a single
+// set of conditions serves for every rule, only the column to be matched 
+// varying. Names suppressed in the curation store (with a null
+// name_to_match) are never coded by the rules. Parameters $2 to $7 are
+// bound by bind_rule, $1 being the lang code.
+// As in the functions the rules replaced, exclude_countries requires an org
+// to have at least one country that is not excluded (so orgs without any
+// country are not coded by such rules). exclude_subdivisions (ISO 3166-2
+// codes, e.g. 'ES-CT') is stricter, and excludes an org with a location in
+// any of the subdivisions listed.
+
+pub fn rule_conditions(rule: &LangRule) -> String {
+
+    let col = rule.match_on.column();
+    format!(r#"n.lang_code is null
+            and n.name_to_match is not null
+            and n.{col} like any($2)
+            and not (n.{col} like any($3))
+            and n.name_type <> all($4)
+            and (cardinality($5::varchar[]) = 0
+                 or exists (select 1 from ext.org_countries c where c.id = n.id and c.country_code = any($5)))
+            and (cardinality($6::varchar[]) = 0
+                 or exists (select 1 from ext.org_countries c where c.id = n.id and c.country_code <> all($6)))
+            and (cardinality($7::varchar[]) = 0
+                 or not exists (select 1 from ext.locations l where l.id = n.id
+                                and l.country_code || '-' || l.csubdiv_code = any($7)))"#)
+}
+
+
+pub fn bind_rule<'q>(query: Query<'q, Postgres, PgArguments>, rule: &'q LangRule) -> Query<'q, Postgres, PgArguments> {
+    query
+        .bind(&rule.lang_code)
+        .bind(&rule.include)
+        .bind(&rule.exclude)
+        .bind(&rule.skip_name_types)
+        .bind(&rule.countries)
+        .bind(&rule.exclude_countries)
+        .bind(&rule.exclude_subdivisions)
+}
+
+
+fn rule_sql(rule: &LangRule) -> String {
+
+    let col = rule.match_on.column();
+    let update_sql = format!(r#"update ext.names n
+            set lang_code = $1
+            where {}"#, rule_conditions(rule));
+
+    let pattern_expr = format!(r#"(select u.p from unnest($2::varchar[]) with ordinality as u(p, i)
+                where n.{col} like u.p order by u.i limit 1)"#);
+    with_provenance(&update_sql, "lex_auto", &rule.name, &pattern_expr)
+}
+
+
+pub async fn apply_lang_rules(data_folder: &Path, write_defaults: bool, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let rules = get_rules_file(data_folder, write_defaults)?.rules;
+    check_lang_codes(&rules, conn).await?;
+    rule_conflicts::record_rule_matches(&rules, conn).await?;
+    let mut total_records_affected = 0;
+
+    for rule in &rules {
+        let sql = rule_sql(rule);
+        let res = bind_rule(sqlx::query(&sql), rule)
+            .execute(&mut *conn).await
+            .map_err(|e| AppError::SqlxError(e, sql))?;
+        info!("{} language codes ('{}') added by rule '{}'", res.rows_affected(), rule.lang_code, rule.name);
+        total_records_affected += res.rows_affected();
+    }
+
+    info!("{} language codes added by {} language rules", total_records_affected, rules.len());
+    rule_conflicts::report_conflicts(conn).await?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_default_rules_are_valid() {
+        let rules_file = parse_rules_file(DEFAULT_RULES, "default rules").unwrap();
+        assert_eq!(rules_file.version, DEFAULT_RULES_VERSION);
+        let rules = rules_file.rules;
+        assert!(rules.len() > 20);
+        assert_eq!(rules[0].name, "german lexicon");
+        assert!(rules.iter().position(|r| r.name == "dutch lexicon") < rules.iter().position(|r| r.name == "english schools"));
+        assert!(rules.windows(2).all(|w| w[0].priority >= w[1].priority));
+
+        let french = rules.iter().find(|r| r.name == "french unit prefixes").unwrap();
+        assert_eq!(french.match_on, MatchField::Name);
+        assert_eq!(french.countries, vec!["FR", "PF"]);
+        assert_eq!(french.skip_name_types, vec![10]);
+
+        for lang_code in ["de", "es", "pt", "it", "nl", "pl", "cs", "tr", "sv", "da", "fi", "id"] {
+            assert!(rules.iter().any(|r| r.lang_code == lang_code && !r.countries.is_empty()),
+                    "no country scoped rule for '{}'", lang_code);
+        }
+    }
+
+    // The rules being applied in turn, so that the default rules can be
+    // checked against example names.
+
+    // Locations are given as a country code, or as a country and subdivision
+    // code (e.g. 'ES-GA').
+
+    fn coded_as<'a>(rules: &'a [LangRule], name: &str, locations: &[&str]) -> Option<&'a str> {
+        let countries: Vec<&str> = locations.iter().map(|l| l.split('-').next().unwrap()).collect();
+        rules.iter().find(|r| {
+            let value = match r.match_on {
+                MatchField::NameToMatch => name.to_lowercase(),
+                MatchField::Name => name.to_string(),
+            };
+            like_any(&value, &r.include) && !like_any(&value, &r.exclude)
+                && (r.countries.is_empty() || countries.iter().any(|c| r.countries.iter().any(|rc| rc == c)))
+                && (r.exclude_countries.is_empty() || countries.iter().any(|c| !r.exclude_countries.iter().any(|rc| rc == c)))
+                && !locations.iter().any(|l| r.exclude_subdivisions.iter().any(|rs| rs == l))
+        }).map(|r| r.lang_code.as_str())
+    }
+
+    #[test]
+    fn check_english_names_not_coded_by_lexicons() {
+        let rules = parse_rules_file(DEFAULT_RULES, "default rules").unwrap().rules;
+        assert_eq!(coded_as(&rules, "Deutsches Institute for Economic Research", &["DE"]), Some("en"));
+        assert_eq!(coded_as(&rules, "Deutsches Institut für Wirtschaftsforschung", &["DE"]), Some("de"));
+        assert_eq!(coded_as(&rules, "The Netherlands Cancer Institute", &["NL"]), Some("en"));
+        assert_eq!(coded_as(&rules, "Nederlands Kanker Instituut", &["NL"]), Some("nl"));
+        assert_eq!(coded_as(&rules, "Hogeschool Utrecht", &["NL"]), Some("nl"));
+        assert_eq!(coded_as(&rules, "São Paulo State University", &["BR"]), Some("en"));
+        assert_eq!(coded_as(&rules, "Universidade Estadual Paulista", &["BR"]), Some("pt"));
+        assert_eq!(coded_as(&rules, "Rigshospitalet", &["DK"]), Some("da"));
+        assert_eq!(coded_as(&rules, "Institut for Matematik", &["DK"]), Some("da"));
+    }
+
+    #[test]
+    fn check_spanish_lexicon_leaves_other_languages_of_spain() {
+        let rules = parse_rules_file(DEFAULT_RULES, "default rules").unwrap().rules;
+        assert_eq!(coded_as(&rules, "Universidad Complutense de Madrid", &["ES-MD"]), Some("es"));
+        assert_eq!(coded_as(&rules, "Universidad de Santiago de Compostela", &["ES-GA"]), None);
+        assert_eq!(coded_as(&rules, "Universidade de Vigo", &["ES-GA"]), None);
+        assert_eq!(coded_as(&rules, "Museu Nacional d'Art de Catalunya", &["ES-CT"]), None);
+        assert_eq!(coded_as(&rules, "Universidad del País Vasco", &["ES-MD", "ES-PV"]), None);
+
+        // Anchored patterns do not match the Galician and Portuguese forms
+        assert_eq!(coded_as(&rules, "Universidade de Vigo", &["ES"]), None);
+        assert_eq!(coded_as(&rules, "Universidad de Sevilla", &["ES"]), Some("es"));
+    }
+
+    #[test]
+    fn check_rules_sorted_by_priority_then_file_order() {
+        let rules_string = r#"
+            [[rules]]
+            name = "low"
+            lang_code = "de"
+            priority = 1
+            include = ["%universität%"]
+
+            [[rules]]
+            name = "high"
+            lang_code = "en"
+            priority = 5
+            include = ["%university%"]
+            exclude = ["%universität%"]
+
+            [[rules]]
+            name = "low 2"
+            lang_code = "nl"
+            priority = 1
+            include = ["%universiteit%"]
+            match_on = "name"
+        "#;
+        let rules = parse_rules_file(rules_string, "test").unwrap().rules;
+        let names: Vec<&str> = rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["high", "low", "low 2"]);
+        assert!(rules[1].countries.is_empty() && rules[1].exclude.is_empty());
+        assert_eq!(rules[1].match_on, MatchField::NameToMatch);
+        assert!(rule_sql(&rules[2]).contains("n.name like any($2)"));
+    }
+
+    #[test]
+    fn check_invalid_rules_are_rejected() {
+        let no_patterns = r#"
+            [[rules]]
+            name = "empty"
+            lang_code = "en"
+            priority = 1
+            include = []
+        "#;
+        assert!(matches!(parse_rules_file(no_patterns, "test"), Err(AppError::RulesError(_, _))));
+
+        let duplicate_names = r#"
+            [[rules]]
+            name = "same"
+            lang_code = "en"
+            priority = 1
+            include = ["%a%"]
+
+            [[rules]]
+            name = "same"
+            lang_code = "fr"
+            priority = 2
+            include = ["%b%"]
+        "#;
+        assert!(matches!(parse_rules_file(duplicate_names, "test"), Err(AppError::RulesError(_, _))));
+
+        let misspelt_field = r#"
+            [[rules]]
+            name = "typo"
+            lang_code = "en"
+            priority = 1
+            include = ["%a%"]
+            countrys = ["GB"]
+        "#;
+        assert!(matches!(parse_rules_file(misspelt_field, "test"), Err(AppError::RulesError(_, _))));
+
+        let bad_threshold = r#"
+            [[rules]]
+            name = "ok"
+            lang_code = "en"
+            priority = 1
+            include = ["%a%"]
+
+            [lang_model]
+            threshold = 1.5
+        "#;
+        assert!(matches!(parse_rules_file(bad_threshold, "test"), Err(AppError::RulesError(_, _))));
+    }
+
+    #[test]
+    fn check_model_settings_read_from_rules_file() {
+        let rules_file = parse_rules_file(DEFAULT_RULES, "default rules").unwrap();
+        assert_eq!(rules_file.lang_model, ModelSettings::default());
+
+        let rules_string = r#"
+            [[rules]]
+            name = "ok"
+            lang_code = "en"
+            priority = 1
+            include = ["%a%"]
+
+            [lang_model]
+            threshold = 0.99
+            training_sources = ["ror"]
+        "#;
+        let settings = parse_rules_file(rules_string, "test").unwrap().lang_model;
+        assert_eq!(settings.threshold, 0.99);
+        assert_eq!(settings.training_sources, vec!["ror"]);
+        assert_eq!(settings.min_training_names, 50);
+    }
+}
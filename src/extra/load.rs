@@ -1,24 +1,24 @@
-use sqlx::{Pool, Postgres};
+use sqlx::PgConnection;
 use crate::AppError;
 use log::info;
 
 
-async fn execute_sql(sql: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+async fn execute_sql(sql: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
     
-    let res = sqlx::raw_sql(&sql).execute(pool)
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
         .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
     Ok(res.rows_affected())
 }
 
 
-pub async fn create_ext_schema(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+pub async fn create_ext_schema(conn: &mut PgConnection) -> Result<u64, AppError> {
 
     execute_sql(r#"SET client_min_messages TO WARNING; 
-    create schema if not exists ext;"#, pool).await
+    create schema if not exists ext;"#, conn).await
 }
 
 
-pub async fn load_orgs(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn load_orgs(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"drop table if exists ext.orgs;
             create table ext.orgs
@@ -33,7 +33,7 @@ pub async fn load_orgs(pool: &Pool<Postgres>) -> Result<(), AppError> {
         , country_code      varchar     null
     );"#;
 
-    execute_sql(sql, pool).await?;
+    execute_sql(sql, conn).await?;
     
     let sql = r#"insert into ext.orgs (id, ror_full_id, ror_name, 
             status, established, location, csubdiv_code, country_code)
@@ -41,7 +41,7 @@ pub async fn load_orgs(pool: &Pool<Postgres>) -> Result<(), AppError> {
             status, established, location, csubdiv_code, country_code
             from src.core_data;"#;
         
-    let res = execute_sql(sql, pool).await?;
+    let res = execute_sql(sql, conn).await?;
     info!("{} organisation records transferred to ext schema", res);
 
     Ok(())
@@ -49,7 +49,7 @@ pub async fn load_orgs(pool: &Pool<Postgres>) -> Result<(), AppError> {
 }
 
 
-pub async fn load_names(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn load_names(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"drop table if exists ext.names;
             create table ext.names
@@ -62,25 +62,26 @@ pub async fn load_names(pool: &Pool<Postgres>) -> Result<(), AppError> {
         , lang_code         varchar     null
         , lang_source       varchar     null
         , script_code       varchar     null
+        , secondary_script  varchar     null
     );
     create index names_idx on ext.names(id);"#;
 
-    execute_sql(sql, pool).await?;
+    execute_sql(sql, conn).await?;
 
     let sql = r#"insert into ext.names (id, name, name_to_match, name_type, 
-            is_ror_name, lang_code, script_code)
+            is_ror_name, lang_code, lang_source, script_code, secondary_script)
             select id, value, lower(value), name_type, 
-            is_ror_name, lang_code, script_code
+            is_ror_name, lang_code, lang_source, script_code, secondary_script
             from src.names;"#;
         
-    let res = execute_sql(sql, pool).await?;
+    let res = execute_sql(sql, conn).await?;
     info!("{} organisation names transferred to ext schema", res);
 
     Ok(())
 }
 
 
-pub async fn load_rels(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn load_rels(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"drop table if exists ext.relationships;
             create table ext.relationships
@@ -93,7 +94,7 @@ pub async fn load_rels(pool: &Pool<Postgres>) -> Result<(), AppError> {
             );  
             create index relationships_idx on ext.relationships(id);"#;
 
-    execute_sql(sql, pool).await?;
+    execute_sql(sql, conn).await?;
     
     let sql = r#"insert into ext.relationships (id, ror_name, rel_type, 
             related_id, related_name)
@@ -101,14 +102,14 @@ pub async fn load_rels(pool: &Pool<Postgres>) -> Result<(), AppError> {
             related_id, related_name
             from src.relationships;"#;
         
-    let res = execute_sql(sql, pool).await?;
+    let res = execute_sql(sql, conn).await?;
     info!("{} relationship records transferred to ext schema", res);
         
     Ok(())
 }
 
 
-pub async fn load_types(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn load_types(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"drop table if exists ext.type;
             create table ext.type
@@ -119,21 +120,21 @@ pub async fn load_types(pool: &Pool<Postgres>) -> Result<(), AppError> {
             );  
             create index type_idx on ext.type(id);"#;
 
-    execute_sql(sql, pool).await?;
+    execute_sql(sql, conn).await?;
     
     
             let sql = r#"insert into ext.type(id, ror_name, org_type)
             select id, ror_name, org_type
             from src.type;"#;
         
-    let res = execute_sql(sql, pool).await?;
+    let res = execute_sql(sql, conn).await?;
     info!("{} type records transferred to ext schema", res);
         
     Ok(())
 }
 
 
-pub async fn load_locs(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn load_locs(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"drop table if exists ext.locations;
             create table ext.locations
@@ -153,7 +154,7 @@ pub async fn load_locs(pool: &Pool<Postgres>) -> Result<(), AppError> {
             );
             create index locations_idx on ext.locations(id);"#;
 
-    execute_sql(sql, pool).await?;
+    execute_sql(sql, conn).await?;
         
     let sql = r#"insert into ext.locations(id, ror_name, 
                 geonames_id, location, lat, lng, cont_code, 
@@ -165,7 +166,7 @@ pub async fn load_locs(pool: &Pool<Postgres>) -> Result<(), AppError> {
                 csubdiv_code, csubdiv_name
             from src.locations;"#;
         
-    let res = execute_sql(sql, pool).await?;
+    let res = execute_sql(sql, conn).await?;
     info!("{} location records transferred to ext schema", res);
     
     let sql = r#"drop table if exists ext.org_countries;
@@ -176,21 +177,21 @@ pub async fn load_locs(pool: &Pool<Postgres>) -> Result<(), AppError> {
             );
             create index countries_idx on ext.org_countries(id);"#;
 
-    sqlx::raw_sql(sql).execute(pool)
+    sqlx::raw_sql(sql).execute(&mut *conn)
             .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
         
             let sql = r#"insert into ext.org_countries(id, country_code)
             select distinct id, country_code
             from ext.locations;"#;
         
-    let res = sqlx::raw_sql(sql).execute(pool)
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
             .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
     info!("{} country records created", res.rows_affected());
 
     Ok(())
 }
 
-pub async fn reset_postgres_messaging(pool: &Pool<Postgres>) -> Result<u64, AppError> {
-    execute_sql(r#"SET client_min_messages TO NOTICE;"#, pool).await
+pub async fn reset_postgres_messaging(conn: &mut PgConnection) -> Result<u64, AppError> {
+    execute_sql(r#"SET client_min_messages TO NOTICE;"#, conn).await
 }
 
@@ -0,0 +1,233 @@
+/***************************************************************************
+ * Manual language coding. The names still without a lang code after the
+ * additional processing can be exported (-u) to a csv file in the outputs
+ * folder, listing each name's id, name, name_to_match, type, country,
+ * script and org types, with empty lang_code and notes columns. Once the
+ * lang codes have been added the file should be saved in the data folder
+ * as manual_lang_codes.csv (rows from later exports being appended to it).
+ * That file is read at the start of each run of the additional processing
+ * (-q) and its codes applied, immediately after those from ROR, with the
+ * lang_source 'manual'. Rows are matched on the ror id and exact name, so
+ * that the file can be replayed against any later ROR version, and rows
+ * whose name no longer exists are reported.
+ ***************************************************************************/
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use serde::Deserialize;
+use sqlx::PgConnection;
+use log::{info, warn};
+use crate::AppError;
+use crate::import::copy_writer::{copy_rows_on_conn, CopyRows, CopyWriter};
+use super::provenance::with_provenance;
+
+
+pub const MANUAL_FILE_NAME: &str = "manual_lang_codes.csv";
+pub const MANUAL_STEP: &str = "manual";
+
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct ManualCode {
+    pub id: String,
+    pub name: String,
+    pub lang_code: Option<String>,
+}
+
+
+#[derive(Default)]
+struct ManualCodeVecs {
+    ids: Vec<String>,
+    names: Vec<String>,
+    lang_codes: Vec<String>,
+    lines: Vec<i32>,
+}
+
+impl CopyRows for ManualCodeVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ext.manual_codes (id, name, lang_code, line) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.names[i]).field(&self.lang_codes[i]).field(&self.lines[i]);
+            w.end_row();
+        }
+    }
+}
+
+
+pub async fn export_uncoded_names(file_path: &Path, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select n.id, n.name, n.name_to_match, t.name as name_type,
+            o.country_code, n.script_code,
+            (select string_agg(ot.name, ', ' order by ot.id)
+             from ext.type y
+             inner join lup.ror_org_types ot on y.org_type = ot.id
+             where y.id = n.id) as org_types
+            from ext.names n
+            inner join ext.orgs o on n.id = o.id
+            left join lup.ror_name_types t on n.name_type = t.id
+            where n.lang_code is null
+            order by o.country_code, n.id, n.name_type, n.name;"#;
+
+    type UncodedRow = (String, String, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>);
+    let rows: Vec<UncodedRow> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut wtr = csv::Writer::from_path(file_path)
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.to_owned()))?;
+    wtr.write_record(["id", "name", "name_to_match", "name_type", "country_code", "script_code",
+                      "org_types", "lang_code", "notes"])
+        .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.to_owned()))?;
+    for (id, name, name_to_match, name_type, country_code, script_code, org_types) in &rows {
+        wtr.write_record([id, name, &opt_text(name_to_match), &opt_text(name_type), &opt_text(country_code),
+                          &opt_text(script_code), &opt_text(org_types), "", ""])
+            .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), file_path.to_owned()))?;
+    }
+    wtr.flush().map_err(|e| AppError::IoWriteErrorWithPath(e, file_path.to_owned()))?;
+
+    info!("{} uncoded names written to {}", rows.len(), file_path.display());
+    Ok(())
+}
+
+fn opt_text(s: &Option<String>) -> String {
+    s.clone().unwrap_or_default()
+}
+
+
+// Returns the rows with a lang code, each with its line number in the file
+// (the header being line 1), and the number of duplicate rows ignored. Any
+// other columns in the file, such as those in the export, are ignored.
+
+pub fn read_manual_codes<R: io::Read>(rdr: R) -> Result<(Vec<(ManualCode, i32)>, usize), csv::Error> {
+
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(rdr);
+    let mut codes: Vec<(ManualCode, i32)> = Vec::new();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut num_duplicates = 0;
+
+    for (i, result) in reader.deserialize().enumerate() {
+        let code: ManualCode = result?;
+        if code.lang_code.as_deref().is_none_or(str::is_empty) {
+            continue;
+        }
+        if !seen.insert((code.id.clone(), code.name.clone())) {
+            num_duplicates += 1;
+            continue;
+        }
+        codes.push((code, i as i32 + 2));
+    }
+    Ok((codes, num_duplicates))
+}
+
+
+pub async fn apply_manual_codes(data_folder: &Path, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let file_path = data_folder.join(MANUAL_FILE_NAME);
+    if !file_path.exists() {
+        info!("No manual coding file ({}) in the data folder", MANUAL_FILE_NAME);
+        return Ok(());
+    }
+
+    let file = std::fs::File::open(&file_path)
+        .map_err(|e| AppError::IoReadErrorWithPath(e, file_path.clone()))?;
+    let (codes, num_duplicates) = read_manual_codes(file)
+        .map_err(|e| AppError::IoReadErrorWithPath(e.into(), file_path.clone()))?;
+    if num_duplicates > 0 {
+        warn!("{} duplicate rows (same id and name) in {} ignored - the first row for each name is used",
+              num_duplicates, MANUAL_FILE_NAME);
+    }
+
+    let mut mv = ManualCodeVecs::default();
+    for (code, line) in codes {
+        mv.ids.push(code.id);
+        mv.names.push(code.name);
+        mv.lang_codes.push(code.lang_code.unwrap_or_default());
+        mv.lines.push(line);
+    }
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.manual_codes;
+            create table ext.manual_codes
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , lang_code         varchar     not null
+        , line              int         not null
+    );
+    create index manual_codes_idx on ext.manual_codes(id, name);
+    SET client_min_messages TO NOTICE;"#;
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    copy_rows_on_conn(&mv, conn).await?;
+
+    // Codes not in the lookup table are likely to be mistakes, and are not applied.
+
+    let sql = r#"select lang_code, count(*)
+            from ext.manual_codes
+            where lang_code not in (select code from lup.lang_codes)
+            group by lang_code
+            order by lang_code;"#;
+    let unknown: Vec<(String, i64)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    for (lang_code, num) in &unknown {
+        warn!("{} manual codes of '{}' not applied - not a known language code", num, lang_code);
+    }
+
+    let update_sql = r#"update ext.names n
+            set lang_code = m.lang_code
+            from ext.manual_codes m
+            where n.id = m.id and n.name = m.name
+            and n.lang_code is null
+            and m.lang_code in (select code from lup.lang_codes)"#;
+    let sql = with_provenance(update_sql, MANUAL_STEP, "manual_lang_codes", "'line ' || m.line");
+    let res = sqlx::raw_sql(&sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+    info!("{} language codes applied from {} ({} rows with codes)", res.rows_affected(), MANUAL_FILE_NAME, mv.ids.len());
+
+    let sql = r#"select m.line, m.id, m.name
+            from ext.manual_codes m
+            where not exists (select 1 from ext.names n where n.id = m.id and n.name = m.name)
+            order by m.line;"#;
+    let missing: Vec<(i32, String, String)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !missing.is_empty() {
+        warn!("{} rows in {} refer to names that no longer exist in the ROR data:", missing.len(), MANUAL_FILE_NAME);
+        for (line, id, name) in missing {
+            warn!("    line {}: {} '{}'", line, id, name);
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_manual_codes_read_from_curated_export() {
+        let curated = "id,name,name_to_match,name_type,country_code,script_code,org_types,lang_code,notes\n\
+                       04abc1234,Hochschule München,hochschule münchen,label,DE,Latn,education, de ,checked\n\
+                       04abc1234,HM,hm,acronym,DE,Latn,education,,\n\
+                       05xyz9876,\"Politecnico di Milano, Polimi\",politecnico di milano polimi,alias,IT,Latn,education,it,\n\
+                       04abc1234,Hochschule München,hochschule münchen,label,DE,Latn,education,en,\n";
+        let (codes, num_duplicates) = read_manual_codes(curated.as_bytes()).unwrap();
+
+        assert_eq!(num_duplicates, 1);
+        assert_eq!(codes.len(), 2);
+        assert_eq!(codes[0], (ManualCode { id: "04abc1234".to_string(), name: "Hochschule München".to_string(),
+                                           lang_code: Some("de".to_string()) }, 2));
+        assert_eq!(codes[1].0.name, "Politecnico di Milano, Polimi");
+        assert_eq!(codes[1].1, 4);
+    }
+
+    #[test]
+    fn check_only_id_name_and_lang_code_are_required() {
+        let minimal = "lang_code,name,id\nfr,Université Laval,04sjchr03\n";
+        let (codes, _) = read_manual_codes(minimal.as_bytes()).unwrap();
+        assert_eq!(codes[0].0.lang_code.as_deref(), Some("fr"));
+        assert_eq!(codes[0].1, 2);
+    }
+}
@@ -1,84 +1,229 @@
+/***************************************************************************
+ * The extra module carries out the additional processing (-q), which
+ * copies the src data to the ext schema, prepares the names for matching,
+ * and then adds language codes to as many names as possible. All steps
+ * run on a single connection. In a dry run (-n) that connection is within
+ * a transaction that is rolled back at the end, so that nothing is stored,
+ * but the language codes that would have been applied, for each step and 
+ * language, are first reported, with samples of the names involved.
+ * An evaluation (-e) also runs in a rolled back transaction, and measures 
+ * the language heuristics against the lang codes supplied by ROR. The names 
+ * left uncoded can be exported (-u) for manual coding, the results of which
+ * are applied in later runs. Corrections held in the curation store (the
+ * 'cur' schema, which is never dropped) are applied on every run.
+ ***************************************************************************/
+
 mod load;
 mod prep;
 mod names;
 mod acros;
+mod manual;
+mod curation;
+mod lang_rules;
+mod lang_model;
+mod region_langs;
+mod rule_conflicts;
+mod provenance;
+mod evaluation;
 
-use sqlx::{Pool, Postgres};
+use std::path::Path;
+use sqlx::{Pool, Postgres, PgConnection};
 use crate::AppError;
 use log::info;
 
 
-pub async fn load_data(pool : &Pool<Postgres>) -> Result<(), AppError>
+pub async fn code_names(data_folder: &Path, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let mut conn = pool.acquire().await
+        .map_err(|e| AppError::SqlxError(e, "Acquiring connection for additional processing".to_string()))?;
+    run_name_coding(data_folder, true, &mut conn).await
+}
+
+
+pub async fn dry_run_code_names(data_folder: &Path, pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    // The current (stored) results are obtained first, for comparison. Any
+    // default rules files missing from the data folder are used but not written.
+
+    let current = provenance::fetch_current_counts(pool).await?;
+
+    let mut tx = pool.begin().await
+        .map_err(|e| AppError::SqlxError(e, "Starting dry run transaction".to_string()))?;
+    run_name_coding(data_folder, false, &mut tx).await?;
+    provenance::report_dry_run(&current, &mut tx).await?;
+    tx.rollback().await
+        .map_err(|e| AppError::SqlxError(e, "Rolling back dry run transaction".to_string()))?;
+
+    info!("Dry run complete - all changes rolled back");
+    Ok(())
+}
+
+
+pub async fn evaluate_coding(data_folder: &Path, output_folder: &Path, data_version: &str, 
+                             pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let mut tx = pool.begin().await
+        .map_err(|e| AppError::SqlxError(e, "Starting evaluation transaction".to_string()))?;
+    load_data(&mut tx).await?;
+    prep_names(&mut tx).await?;
+
+    // The ror lang codes are removed, and the heuristics applied in their absence.
+
+    let num_gold = evaluation::hide_gold_codes(&mut tx).await?;
+    info!("{} ror language codes hidden for evaluation", num_gold);
+
+    // The same steps as in the normal processing are run, other than those
+    // that are not language heuristics (the manual and 'cm' coding, and the 
+    // curation store). With the ror codes hidden the language model has no
+    // training names, unless heuristic sources have been added to its
+    // training_sources, and so is not applied.
+
+    apply_name_codes(data_folder, true, true, &mut tx).await?;
+    apply_acro_codes(&mut tx).await?;
+
+    let rows = evaluation::fetch_eval_rows(&mut tx).await?;
+    tx.rollback().await
+        .map_err(|e| AppError::SqlxError(e, "Rolling back evaluation transaction".to_string()))?;
+
+    let file_stem = format!("ror {} language coding evaluation", data_version);
+    evaluation::write_evaluation(&rows, &file_stem, output_folder)
+}
+
+
+pub async fn export_uncoded_names(data_version: &str, output_folder: &Path, 
+                                  pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let mut conn = pool.acquire().await
+        .map_err(|e| AppError::SqlxError(e, "Acquiring connection for uncoded names export".to_string()))?;
+    let file_path = output_folder.join(format!("ror {} uncoded names.csv", data_version));
+    manual::export_uncoded_names(&file_path, &mut conn).await
+}
+
+
+async fn run_name_coding(data_folder: &Path, write_defaults: bool, conn: &mut PgConnection) -> Result<(), AppError>
+{
+    load_data(conn).await?;
+    apply_curated_data(conn).await?;
+    prep_names(conn).await?;
+    suppress_curated_names(conn).await?;
+    apply_name_codes(data_folder, write_defaults, false, conn).await?;
+    apply_curated_lang_codes(conn).await?;
+    apply_acro_codes(conn).await?;
+    curation::report_stale_curation(conn).await?;
+    provenance::report_provenance(conn).await?;
+
+    // complete_rels(conn).await?;
+    // rationalise_companies(conn).await?;
+
+    Ok(())
+}
+
+
+async fn load_data(conn: &mut PgConnection) -> Result<(), AppError>
 {
     // These simply load the src data into matching tables in the 'ext' schema
     // of the ror DB. 
       
-    load::create_ext_schema(pool).await?;
-    load::load_orgs(pool).await?;
-    load::load_names(pool).await?;
-    load::load_rels(pool).await?;
-    load::load_types(pool).await?;
-    load::load_locs(pool).await?;
-    load::reset_postgres_messaging(pool).await?;
+    load::create_ext_schema(conn).await?;
+    provenance::create_provenance_table(conn).await?;
+    load::load_orgs(conn).await?;
+    load::load_names(conn).await?;
+    load::load_rels(conn).await?;
+    load::load_types(conn).await?;
+    load::load_locs(conn).await?;
+    load::reset_postgres_messaging(conn).await?;
 
     Ok(())
 }
 
 
-pub async fn prep_names(pool : &Pool<Postgres>) -> Result<(), AppError>
+async fn apply_curated_data(conn: &mut PgConnection) -> Result<(), AppError>
+{
+    // Corrected org types and additional names from the curation store are 
+    // added to the loaded data, so that all the later steps make use of them.
+
+    curation::create_curation_tables(conn).await?;
+    curation::apply_org_type_corrections(conn).await?;
+    curation::add_curated_names(conn).await?;
+
+    Ok(())
+}
+
+
+async fn prep_names(conn: &mut PgConnection) -> Result<(), AppError>
 {
     // preparation of org names and addition of a 'name_to_match' field
     
-    prep::remove_no_width_chars(pool).await?;
-    prep::remove_peoples_space_in_names(pool).await?;
+    prep::remove_no_width_chars(conn).await?;
+    prep::remove_peoples_space_in_names(conn).await?;
 
     // The 'name_to_match' form is lower-cased, shorn of full stops, 
     // commas and brackets, and has apostrophes replaced by single right quotes,
     // along with other 'standardising'  measures.
 
-    prep::prepare_names_to_match(pool).await?;
+    prep::prepare_names_to_match(conn).await?;
 
     Ok(())
 }
 
 
-pub async fn apply_name_codes(pool : &Pool<Postgres>) -> Result<(), AppError>
+// For an evaluation, heuristics_only skips the steps that do not predict a
+// language from the name and its org, so that only the heuristics are measured.
+
+async fn apply_name_codes(data_folder: &Path, write_defaults: bool, heuristics_only: bool, 
+                          conn: &mut PgConnection) -> Result<(), AppError>
 {
-    // Ascribe source to those with an existing lang code
+    // Ascribe source to those with an existing lang code. Each lang code 
+    // applied, from whatever source, is recorded in ext.lang_provenance,
+    // with the step and the specific rule that applied it.
 
-    update_lang_code_source("ror", pool).await?;
+    update_lang_code_source("ror", conn).await?;
+    provenance::record_ror_codes(conn).await?;
 
-    // Update lang codes from scripts where possible, record lang code source type
+    // Codes added by hand to a previous export of the uncoded names, and saved 
+    // in the data folder, are applied next, so that they take precedence over
+    // all the automatic steps.
 
-    names::add_langs_for_nonlatin_codes(pool).await?;
-    update_lang_code_source("script_auto", pool).await?;
+    if !heuristics_only {
+        manual::apply_manual_codes(data_folder, conn).await?;
+        update_lang_code_source(manual::MANUAL_STEP, conn).await?;
+    }
+
+    // Update lang codes from scripts where possible (where the script is only
+    // used by one language), record lang code source type
+
+    names::add_langs_for_nonlatin_codes(conn).await?;
+    update_lang_code_source("script_auto", conn).await?;
     
     // If the org is a commercial company change the lang code to 'cm'
     // This makes it easier to see the gaps, though 'cm' needs to be added to the lang codes
-    // This also over-rides any previous application of a language code to a company name
+    // This also over-rides any previous application of a language code to a company name,
+    // other than a manual code
    
-    names::add_cm_lang_code_to_comm_orgs(pool).await?;
+    if !heuristics_only {
+        names::add_cm_lang_code_to_comm_orgs(conn).await?;
+    }
     
-    // Add languages if possible, using location of org and key words or word parts
-    // Do language of acronyms where all other names have the same language
-    // See what are left
+    // Add languages if possible, using location of org and key words or word parts,
+    // as specified by the rules in the lang_rules.toml file in the data folder.
   
-    names::update_english_names(pool).await?;
-    names::update_japanese_names(pool).await?;
-    names::update_chinese_names(pool).await?;
-    names::update_french_names(pool).await?;
-    names::update_indian_names(pool).await?;
-    names::update_iranian_names(pool).await?;
-    names::update_russian_names(pool).await?;
-    names::update_ukrainian_names(pool).await?;
-    names::update_norwegian_names(pool).await?;
-    names::update_serbian_names(pool).await?;
-    names::update_bulgarian_names(pool).await?;
-    names::update_israeli_names(pool).await?;
-    names::update_korean_names(pool).await?;
-    names::update_greek_names(pool).await?;
-
-    update_lang_code_source("lex_auto", pool).await?;
+    lang_rules::apply_lang_rules(data_folder, write_defaults, conn).await?;
+
+    update_lang_code_source("lex_auto", conn).await?;
+
+    // Names still uncoded are then given the language of the org's location, if
+    // that location (country, or subdivision within the country) and the name's
+    // script point to a single language, as specified in region_langs.toml. 
+
+    region_langs::apply_region_codes(data_folder, write_defaults, conn).await?;
+    update_lang_code_source(region_langs::REGION_STEP, conn).await?;
+
+    // Names still uncoded (other than acronyms) are given the language suggested 
+    // by a character n-gram model trained on the names coded so far, if the 
+    // model's confidence reaches the threshold set in lang_rules.toml.
+
+    lang_model::apply_model_codes(data_folder, write_defaults, conn).await?;
+    update_lang_code_source(lang_model::MODEL_STEP, conn).await?;
 
         // israel
         // greece ?
@@ -86,15 +231,8 @@ pub async fn apply_name_codes(pool : &Pool<Postgres>) -> Result<(), AppError>
         // taiwan +
         // india +
         // russia +
-        
-    // Do acronym language codes....
 
     /*
-    
-    names::obtain_manual_coding_list(pool).await?;
-    names::apply_manual_coding_list(pool).await?;
-
-    update_lang_code_source("manual", pool).await?;
 
     // There are about 1600 names that begin with 'The '
     // These are often presented in source material without the 'The '.
@@ -104,7 +242,7 @@ pub async fn apply_name_codes(pool : &Pool<Postgres>) -> Result<(), AppError>
     // This is done after lang codes have been applied to make sure maximum 
     // information is transferred to the new records
 
-    prep::add_names_without_thes(pool).await?;
+    prep::add_names_without_thes(conn).await?;
 
     // Need to modify the company data to make a single entry from multiple national 
     // subsidiaries - get companies in a parent child relationship - 
@@ -115,10 +253,15 @@ pub async fn apply_name_codes(pool : &Pool<Postgres>) -> Result<(), AppError>
 }
 
 
-pub async fn apply_acro_codes(_pool : &Pool<Postgres>) -> Result<(), AppError>
+async fn apply_acro_codes(conn: &mut PgConnection) -> Result<(), AppError>
 {
+    // Acronyms are excluded from the earlier steps, but where all the other names 
+    // of the org have the same language the acronyms are given that language too.
+
+    acros::add_mono_lang_org_codes(conn).await?;
+    update_lang_code_source(acros::MONO_LANG_STEP, conn).await?;
 
-    // Need to consider acronyms (type = 10)
+    // Still to do - acronyms derived from the org's other names (type = 10)
     // get all the acronyms into a table with the id, acronym
     // ???, and space for a matching name and language code
 
@@ -136,14 +279,39 @@ pub async fn apply_acro_codes(_pool : &Pool<Postgres>) -> Result<(), AppError>
 }
 
 
-async fn update_lang_code_source(srce: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+async fn suppress_curated_names(conn: &mut PgConnection) -> Result<(), AppError>
+{
+    // Names suppressed in the curation store have their name_to_match set to
+    // null as soon as it has been prepared, so that they are kept out of the
+    // coding steps (and the training of the language model) as well as
+    // out of matching.
+
+    curation::suppress_curated_names(conn).await?;
+
+    Ok(())
+}
+
+
+async fn apply_curated_lang_codes(conn: &mut PgConnection) -> Result<(), AppError>
+{
+    // The lang codes in the curation store over-ride the results of all the
+    // earlier steps. They are applied before the acronyms are coded, so that
+    // the acronyms of an org are given its curated language.
+
+    curation::apply_curated_lang_codes(conn).await?;
+
+    Ok(())
+}
+
+
+async fn update_lang_code_source(srce: &str, conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = format!(r#"update ext.names
             set lang_source = '{}'
             where lang_source is null
             and lang_code is not null;"#, srce );
  
-    let res = sqlx::raw_sql(&sql).execute(pool)
+    let res = sqlx::raw_sql(&sql).execute(&mut *conn)
             .await.map_err(|e| AppError::SqlxError(e, sql))?;
         info!("{} records updated with '{}' as language source", res.rows_affected(), srce);
 
@@ -1,27 +1,27 @@
-use sqlx::{Pool, Postgres};
+use sqlx::PgConnection;
 use crate::AppError;
 use log::info;
 
 
-pub async fn remove_no_width_chars (pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn remove_no_width_chars (conn: &mut PgConnection) -> Result<(), AppError> {
 
     // remove any of the set of zero width characters 
 
     let mut no_width_chars = 0;
-    no_width_chars += remove_unicode_from_names("200B", pool).await?;  // zero width space
-    no_width_chars += remove_unicode_from_names("200C", pool).await?;  // zero width no join
-    no_width_chars += remove_unicode_from_names("200D", pool).await?;  // zero width join
-    no_width_chars += remove_unicode_from_names("200E", pool).await?;  // left-to-right mark
-    no_width_chars += remove_unicode_from_names("200F", pool).await?;  // right-to-left mark
-    no_width_chars += remove_unicode_from_names("2060", pool).await?;  // word joiner
-    no_width_chars += remove_unicode_from_names("FEFF", pool).await?;  // zero width no-break space / BOM
+    no_width_chars += remove_unicode_from_names("200B", conn).await?;  // zero width space
+    no_width_chars += remove_unicode_from_names("200C", conn).await?;  // zero width no join
+    no_width_chars += remove_unicode_from_names("200D", conn).await?;  // zero width join
+    no_width_chars += remove_unicode_from_names("200E", conn).await?;  // left-to-right mark
+    no_width_chars += remove_unicode_from_names("200F", conn).await?;  // right-to-left mark
+    no_width_chars += remove_unicode_from_names("2060", conn).await?;  // word joiner
+    no_width_chars += remove_unicode_from_names("FEFF", conn).await?;  // zero width no-break space / BOM
     info!("{} no width characters removed from names to match", no_width_chars);
    
     Ok(())
 }
 
 
-pub async fn remove_peoples_space_in_names(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn remove_peoples_space_in_names(conn: &mut PgConnection) -> Result<(), AppError> {
     
     // Some corrections of chinese "people 's" required
 
@@ -29,7 +29,7 @@ pub async fn remove_peoples_space_in_names(pool: &Pool<Postgres>) -> Result<(),
             set name_to_match = replace(name_to_match, 'people ''s', 'people’s')
             where name_to_match like '%people ''s%'; "#;
 
-    let res = sqlx::query(sql).execute(pool).await
+    let res = sqlx::query(sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
 
     info!("{} 'people 's' errors fixed in names to match", res.rows_affected());
@@ -38,167 +38,167 @@ pub async fn remove_peoples_space_in_names(pool: &Pool<Postgres>) -> Result<(),
 }
 
 
-pub async fn prepare_names_to_match  (pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn prepare_names_to_match  (conn: &mut PgConnection) -> Result<(), AppError> {
 
     // punctuation
 
-    info!("{} periods removed from names to match", remove_from_names(".", pool).await?);
-    info!("{} commas removed from names to match", remove_from_names(",", pool).await?);
-    info!("{} colons removed from names to match", remove_from_names(":", pool).await?);
-    info!("{} semi-colons removed from names to match", remove_from_names(";", pool).await?);
+    info!("{} periods removed from names to match", remove_from_names(".", conn).await?);
+    info!("{} commas removed from names to match", remove_from_names(",", conn).await?);
+    info!("{} colons removed from names to match", remove_from_names(":", conn).await?);
+    info!("{} semi-colons removed from names to match", remove_from_names(";", conn).await?);
     
     // brackets
 
-    info!("{} left parantheses replaced by spaces in names to match", replace_in_names("(", " ", pool).await?);
-    info!("{} right parantheses removed from names to match", remove_from_names(")", pool).await?);
-    info!("{} left brackets replaced by spaces in names to match", replace_in_names("[", " ", pool).await?);
-    info!("{} right brackets removed from names to match", remove_from_names("]", pool).await?);
+    info!("{} left parantheses replaced by spaces in names to match", replace_in_names("(", " ", conn).await?);
+    info!("{} right parantheses removed from names to match", remove_from_names(")", conn).await?);
+    info!("{} left brackets replaced by spaces in names to match", replace_in_names("[", " ", conn).await?);
+    info!("{} right brackets removed from names to match", remove_from_names("]", conn).await?);
 
     // double quotes
     
-    info!("{} straight double quotes removed from names to match", remove_unicode_from_names("0022", pool).await?);
-    info!("{} left curved double quotes removed from names to match", remove_unicode_from_names("201C", pool).await?);
-    info!("{} right curved quotes removed from names to match", remove_unicode_from_names("201D", pool).await?);
-    info!("{} left bottom quotes removed from names to match", remove_unicode_from_names("201E", pool).await?);
-    info!("{} left upper reversed quotes removed from names to match", remove_unicode_from_names("201F", pool).await?);
-    info!("{} left guillemets removed from names to match", remove_unicode_from_names("00AB", pool).await?);
-    info!("{} right guillemets removed from names to match", remove_unicode_from_names("00BB", pool).await?);
-    info!("{} twin single apostrophes removed from names to match", remove_from_names("''''", pool).await?);
+    info!("{} straight double quotes removed from names to match", remove_unicode_from_names("0022", conn).await?);
+    info!("{} left curved double quotes removed from names to match", remove_unicode_from_names("201C", conn).await?);
+    info!("{} right curved quotes removed from names to match", remove_unicode_from_names("201D", conn).await?);
+    info!("{} left bottom quotes removed from names to match", remove_unicode_from_names("201E", conn).await?);
+    info!("{} left upper reversed quotes removed from names to match", remove_unicode_from_names("201F", conn).await?);
+    info!("{} left guillemets removed from names to match", remove_unicode_from_names("00AB", conn).await?);
+    info!("{} right guillemets removed from names to match", remove_unicode_from_names("00BB", conn).await?);
+    info!("{} twin single apostrophes removed from names to match", remove_from_names("''''", conn).await?);
 
     // single quotes
 
-    info!("{} low single quotes changed to left single quotes", replace_unicode_in_names("201A", "‘", pool).await?);    
-    info!("{} reverse single quotes changed to left single quotes", replace_unicode_in_names("201B", "‘", pool).await?);
+    info!("{} low single quotes changed to left single quotes", replace_unicode_in_names("201A", "‘", conn).await?);    
+    info!("{} reverse single quotes changed to left single quotes", replace_unicode_in_names("201B", "‘", conn).await?);
 
-    info!("{} modifier turned commas changed to left single quotes", replace_unicode_in_names("02BB", "‘", pool).await?);
-    info!("{} modifier apostrophes changed to right single quotes", replace_unicode_in_names("02BC", "’", pool).await?);
-    info!("{} modifier reversed commas changed to left single quotes", replace_unicode_in_names("02BD", "‘", pool).await?);
-    info!("{} right half rings changed to right single quotes", replace_unicode_in_names("02BE", "’", pool).await?);
-    info!("{} left half rings changed to left single quotes", replace_unicode_in_names("02BF", "‘", pool).await?);
+    info!("{} modifier turned commas changed to left single quotes", replace_unicode_in_names("02BB", "‘", conn).await?);
+    info!("{} modifier apostrophes changed to right single quotes", replace_unicode_in_names("02BC", "’", conn).await?);
+    info!("{} modifier reversed commas changed to left single quotes", replace_unicode_in_names("02BD", "‘", conn).await?);
+    info!("{} right half rings changed to right single quotes", replace_unicode_in_names("02BE", "’", conn).await?);
+    info!("{} left half rings changed to left single quotes", replace_unicode_in_names("02BF", "‘", conn).await?);
 
     // standardise spaces
 
-    info!("{} non breaking spaces changed to spaces", replace_unicode_in_names("00A0", " ", pool).await?);
-    info!("{}  m quad spaces changed to spaces", replace_unicode_in_names("2001", " ", pool).await?);
-    info!("{}  m spaces changed to spaces", replace_unicode_in_names("2002", " ", pool).await?);
-    info!("{}  n spaces changed to spaces", replace_unicode_in_names("2003", " ", pool).await?);
-    info!("{}  punctuation spaces changed to spaces", replace_unicode_in_names("2008", " ", pool).await?);
-    info!("{}  ideographic spaces changed to spaces", replace_unicode_in_names("3000", " ", pool).await?);
+    info!("{} non breaking spaces changed to spaces", replace_unicode_in_names("00A0", " ", conn).await?);
+    info!("{}  m quad spaces changed to spaces", replace_unicode_in_names("2001", " ", conn).await?);
+    info!("{}  m spaces changed to spaces", replace_unicode_in_names("2002", " ", conn).await?);
+    info!("{}  n spaces changed to spaces", replace_unicode_in_names("2003", " ", conn).await?);
+    info!("{}  punctuation spaces changed to spaces", replace_unicode_in_names("2008", " ", conn).await?);
+    info!("{}  ideographic spaces changed to spaces", replace_unicode_in_names("3000", " ", conn).await?);
   
     // apostrophes 
     // At beginning, or after space changed to left single quotes, otherwise to right single quotes
 
-    info!("{} full width apostrophes changed to apostrophes", replace_unicode_in_names("FF01", "''", pool).await?);
-    info!("{} apostrophes after spaces changed to left single quotes", replace_in_names(" ''", " ‘", pool).await?);
+    info!("{} full width apostrophes changed to apostrophes", replace_unicode_in_names("FF01", "''", conn).await?);
+    info!("{} apostrophes after spaces changed to left single quotes", replace_in_names(" ''", " ‘", conn).await?);
 
     let sql  = r#"update ext.names
             set name_to_match = '‘'||substring(name_to_match, 2)
             where name_to_match like '''%'; "#;
-    let res = sqlx::query(&sql).execute(pool).await
+    let res = sqlx::query(sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
     info!("{} apostrophes at beginning of name changed to left single quotes", res.rows_affected());
 
-    info!("{} remaining apostrophes changed to right single quotes", replace_in_names("''", "’", pool).await?);
+    info!("{} remaining apostrophes changed to right single quotes", replace_in_names("''", "’", conn).await?);
     
     // bullet points
 
-    info!("{} bullets changed to spaces in names to match", replace_unicode_in_names("2022", " ", pool).await?);
-    info!("{} hyphen bullets changed to spaces in names to match", replace_unicode_in_names("2043", " ", pool).await?);
-    info!("{} raised dots changed to spaces in names to match", replace_unicode_in_names("2219", " ", pool).await?);
-    info!("{} small square changes to spaces in names to match", replace_unicode_in_names("25AA", " ", pool).await?);
-    info!("{} katakana middle dots changed to spaces in names to match", replace_unicode_in_names("30FB", " ", pool).await?);
+    info!("{} bullets changed to spaces in names to match", replace_unicode_in_names("2022", " ", conn).await?);
+    info!("{} hyphen bullets changed to spaces in names to match", replace_unicode_in_names("2043", " ", conn).await?);
+    info!("{} raised dots changed to spaces in names to match", replace_unicode_in_names("2219", " ", conn).await?);
+    info!("{} small square changes to spaces in names to match", replace_unicode_in_names("25AA", " ", conn).await?);
+    info!("{} katakana middle dots changed to spaces in names to match", replace_unicode_in_names("30FB", " ", conn).await?);
 
     // standardise hyphens
 
-    info!("{} hyphens changed to ascii hyphens in names to match", replace_unicode_in_names("2010", "-", pool).await?);
-    info!("{} non-breaking hyphens changed to hyphens in names to match", replace_unicode_in_names("2011", "-", pool).await?);
-    info!("{} figure dashes changed to hyphens in names to match", replace_unicode_in_names("2012", "-", pool).await?);
-    info!("{} n dashes changed to hyphens in names to match", replace_unicode_in_names("2013", "-", pool).await?);
-    info!("{} m dashes changed to hyphens in names to match", replace_unicode_in_names("2014", "-", pool).await?);
-    info!("{} horizontal bars changed to hyphens in names to match", replace_unicode_in_names("2015", "-", pool).await?);
+    info!("{} hyphens changed to ascii hyphens in names to match", replace_unicode_in_names("2010", "-", conn).await?);
+    info!("{} non-breaking hyphens changed to hyphens in names to match", replace_unicode_in_names("2011", "-", conn).await?);
+    info!("{} figure dashes changed to hyphens in names to match", replace_unicode_in_names("2012", "-", conn).await?);
+    info!("{} n dashes changed to hyphens in names to match", replace_unicode_in_names("2013", "-", conn).await?);
+    info!("{} m dashes changed to hyphens in names to match", replace_unicode_in_names("2014", "-", conn).await?);
+    info!("{} horizontal bars changed to hyphens in names to match", replace_unicode_in_names("2015", "-", conn).await?);
 
     // standardise hyphen spacing
 
-    info!("{} left spaces removed from hyphens", replace_in_names(" -", "-", pool).await?);
-    info!("{} right spaces removed from hyphens", replace_in_names("- ", "-", pool).await?);
+    info!("{} left spaces removed from hyphens", replace_in_names(" -", "-", conn).await?);
+    info!("{} right spaces removed from hyphens", replace_in_names("- ", "-", conn).await?);
    
 
-    info!("{} double spaces replaced by single in names to match", replace_in_names("  ", " ", pool).await?);
+    info!("{} double spaces replaced by single in names to match", replace_in_names("  ", " ", conn).await?);
 
     // Not currently required - may need to check periodically 
-    // info!("{}  3 per m spaces changed to spaces", replace_unicode_char_in_names("2004", " ", pool).await?);
-    // info!("{}  4 per m spaces changed to spaces", replace_unicode_char_in_names("2005", " ", pool).await?);
-    // info!("{}  6 per m spaces changed to spaces", replace_unicode_char_in_names("2006", " ", pool).await?);
-    // info!("{}  figure spaces changed to spaces", replace_unicode_char_in_names("2007", " ", pool).await?);
-    // info!("{}  thin spaces changed to spaces", replace_unicode_char_in_names("2009", " ", pool).await?);
-    // info!("{}  hair spaces changed to spaces", replace_unicode_char_in_names("200A", " ", pool).await?);
-    // info!("{}  narrow non breaking spaces changed to spaces", replace_unicode_char_in_names("202F", " ", pool).await?);
-    // info!("{}  medium mathematical spaces changed to spaces", replace_unicode_char_in_names("205F", " ", pool).await?);
+    // info!("{}  3 per m spaces changed to spaces", replace_unicode_char_in_names("2004", " ", conn).await?);
+    // info!("{}  4 per m spaces changed to spaces", replace_unicode_char_in_names("2005", " ", conn).await?);
+    // info!("{}  6 per m spaces changed to spaces", replace_unicode_char_in_names("2006", " ", conn).await?);
+    // info!("{}  figure spaces changed to spaces", replace_unicode_char_in_names("2007", " ", conn).await?);
+    // info!("{}  thin spaces changed to spaces", replace_unicode_char_in_names("2009", " ", conn).await?);
+    // info!("{}  hair spaces changed to spaces", replace_unicode_char_in_names("200A", " ", conn).await?);
+    // info!("{}  narrow non breaking spaces changed to spaces", replace_unicode_char_in_names("202F", " ", conn).await?);
+    // info!("{}  medium mathematical spaces changed to spaces", replace_unicode_char_in_names("205F", " ", conn).await?);
 
     // final trim of name 
 
     let sql = r#"update ext.names 
     set name_to_match = trim(name_to_match);"#;
-    sqlx::raw_sql(sql).execute(pool)
+    sqlx::raw_sql(sql).execute(&mut *conn)
             .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
     
     Ok(())
 }
 
 
-async fn remove_from_names(char: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+async fn remove_from_names(char: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
 
     let sql  = format!(r#"update ext.names
             set name_to_match = replace(name_to_match, '{}', '')
             where name_to_match like '%{}%'; "#, char, char);
 
-    let res = sqlx::query(&sql).execute(pool).await
+    let res = sqlx::query(&sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql))?;
 
     Ok(res.rows_affected())
 }
 
 
-async fn replace_in_names(char: &str, rep_str: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+async fn replace_in_names(char: &str, rep_str: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
 
     let sql  = format!(r#"update ext.names
             set name_to_match = replace(name_to_match, '{}', '{}')
             where name_to_match like '%{}%'; "#, char, rep_str, char);
 
-    let res = sqlx::query(&sql).execute(pool).await
+    let res = sqlx::query(&sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql))?;
 
     Ok(res.rows_affected())
 }
 
 
-async fn remove_unicode_from_names(unicode: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+async fn remove_unicode_from_names(unicode: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
 
     let sql  = format!(r#"update ext.names
             set name_to_match = replace(name_to_match, U&'\{}', '')
             where name_to_match like U&'%\{}%'; "#, unicode, unicode);
 
-    let res = sqlx::query(&sql).execute(pool).await
+    let res = sqlx::query(&sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql))?;
 
     Ok(res.rows_affected())
 }
 
 
-async fn replace_unicode_in_names(unicode: &str, rep_str: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+async fn replace_unicode_in_names(unicode: &str, rep_str: &str, conn: &mut PgConnection) -> Result<u64, AppError> {
 
     let sql  = format!(r#"update ext.names
             set name_to_match = replace(name_to_match, U&'\{}', '{}')
             where name_to_match like U&'%\{}%'; "#, unicode, rep_str, unicode);
 
-    let res = sqlx::query(&sql).execute(pool).await
+    let res = sqlx::query(&sql).execute(&mut *conn).await
     .map_err(|e| AppError::SqlxError(e, sql))?;
 
     Ok(res.rows_affected())
 }
 
 /* 
-pub async fn add_names_without_thes(pool: &Pool<Postgres>) -> Result<(), AppError> {
+pub async fn add_names_without_thes(conn: &mut PgConnection) -> Result<(), AppError> {
 
     let sql = r#"insert into ext.names (id, name, name_to_match, name_type, lang_code, lang_source, script_code)
         select n.* from 
@@ -215,7 +215,7 @@ pub async fn add_names_without_thes(pool: &Pool<Postgres>) -> Result<(), AppErro
         and n.name_to_match = r.name_to_match
         where r.id is null;"#;
 
-    let res = sqlx::raw_sql(sql).execute(pool)
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
             .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
     info!("{} additional name records added with initial 'the ' removed", res.rows_affected());
   
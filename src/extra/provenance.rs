@@ -0,0 +1,194 @@
+/***************************************************************************
+ * Language code provenance. Every lang code in ext.names is recorded in
+ * ext.lang_provenance, along with the pipeline step that assigned it
+ * (matching the lang_source given to the name: 'ror', 'manual',
+ * 'script_auto', 'cm_brand', 'lex_auto', 'region_auto', 'model_auto',
+ * 'mono lang org', 'curated'), the id of the specific rule used, and, where
+ * relevant, the pattern, region, script or note that triggered the rule.
+ * The updates of ext.names are wrapped in a common table expression so
+ * that the provenance rows are written by the same statement as the lang
+ * codes.
+ * A wrongly coded name can therefore be traced directly to the rule that
+ * coded it, and all the names coded by a suspect rule can be listed.
+ ***************************************************************************/
+
+use std::collections::HashMap;
+use sqlx::{Pool, Postgres, PgConnection};
+use log::info;
+use crate::AppError;
+
+
+const SAMPLE_SIZE: i32 = 5;
+
+
+pub async fn create_provenance_table(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_provenance;
+            create table ext.lang_provenance
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , name_type         int         null
+        , lang_code         varchar     not null
+        , step              varchar     not null
+        , rule              varchar     not null
+        , pattern           varchar     null
+    );
+    create index lang_provenance_idx on ext.lang_provenance(id);
+    create index lang_provenance_rule_idx on ext.lang_provenance(rule);
+    SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(())
+}
+
+
+// The update statement (without a returning clause or terminating semi-colon)
+// must alias ext.names as 'n'. The pattern expression is evaluated against
+// each updated row, and may be 'null'.
+
+pub fn with_provenance(update_sql: &str, step: &str, rule: &str, pattern_expr: &str) -> String {
+
+    format!(r#"with assigned as ({}
+            returning n.id, n.name, n.name_type, n.lang_code, {} as pattern)
+            insert into ext.lang_provenance (id, name, name_type, lang_code, step, rule, pattern)
+            select id, name, name_type, lang_code, '{}', '{}', pattern
+            from assigned;"#, update_sql.trim_end(), pattern_expr, sql_text(step), sql_text(rule))
+}
+
+fn sql_text(s: &str) -> String {
+    s.replace('\'', "''")
+}
+
+
+pub async fn record_ror_codes(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"insert into ext.lang_provenance (id, name, name_type, lang_code, step, rule, pattern)
+            select id, name, name_type, lang_code, 'ror', 'ror_source', null
+            from ext.names
+            where lang_code is not null;"#;
+
+    let res = sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    info!("{} language codes from ror recorded in provenance table", res.rows_affected());
+    Ok(())
+}
+
+
+// Called before a step that over-writes existing lang codes, so that
+// only the most recent assignment of a code is held for each name.
+
+pub async fn remove_superseded(names_filter: &str, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = format!(r#"delete from ext.lang_provenance p
+            using ext.names n
+            where p.id = n.id and p.name = n.name
+            and p.name_type is not distinct from n.name_type
+            and {};"#, names_filter);
+
+    sqlx::raw_sql(&sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+    Ok(())
+}
+
+
+pub async fn report_provenance(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select step, count(*) as num, count(distinct rule) as rules
+            from ext.lang_provenance
+            group by step
+            order by num desc;"#;
+
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    for (step, num, rules) in rows {
+        info!("{} language codes with provenance '{}', from {} rule(s)", num, step, rules);
+    }
+    Ok(())
+}
+
+
+// For dry runs, the counts for each step and language in the stored 
+// provenance table (if it exists) provide a comparison for the dry run results.
+
+pub async fn fetch_current_counts(pool: &Pool<Postgres>) -> Result<HashMap<(String, String), i64>, AppError> {
+
+    let sql = "select to_regclass('ext.lang_provenance') is not null";
+    let table_exists: bool = sqlx::query_scalar(sql).fetch_one(pool)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !table_exists {
+        return Ok(HashMap::new());
+    }
+
+    let sql = r#"select step, lang_code, count(*)
+            from ext.lang_provenance
+            group by step, lang_code;"#;
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(sql).fetch_all(pool)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(rows.into_iter().map(|(step, lang, num)| ((step, lang), num)).collect())
+}
+
+
+pub async fn report_dry_run(current: &HashMap<(String, String), i64>, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"select step, lang_code, count(*),
+            (array_agg(name order by name))[1:$1]
+            from ext.lang_provenance
+            group by step, lang_code
+            order by step, count(*) desc;"#;
+    let rows: Vec<(String, String, i64, Vec<String>)> = sqlx::query_as(sql).bind(SAMPLE_SIZE)
+        .fetch_all(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    info!("DRY RUN - language codes that would be applied, by step and language:");
+    for (step, lang_code, num, samples) in rows {
+        info!("{}", dry_run_line(&step, &lang_code, num, current.get(&(step.clone(), lang_code.clone())), &samples));
+    }
+    Ok(())
+}
+
+
+fn dry_run_line(step: &str, lang_code: &str, num: i64, current: Option<&i64>, samples: &[String]) -> String {
+
+    let current = match current {
+        Some(n) => format!("currently {}", n),
+        None => "currently none".to_string(),
+    };
+    let samples: Vec<String> = samples.iter().map(|s| format!("'{}'", s)).collect();
+    format!("{:<12} {:<4} {:>8} names ({}), e.g. {}", step, lang_code, num, current, samples.join(", "))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_update_is_wrapped_with_provenance_insert() {
+        let update = r#"update ext.names n
+            set lang_code = 'he'
+            where n.lang_code is null
+            and n.script_code in ('Hebr')
+        "#;
+        let sql = with_provenance(update, "script_auto", "he_by_script", "'Hebr'");
+        assert!(sql.starts_with("with assigned as (update ext.names n"));
+        assert!(sql.contains("('Hebr')\n            returning n.id, n.name, n.name_type, n.lang_code, 'Hebr' as pattern)"));
+        assert!(sql.contains("select id, name, name_type, lang_code, 'script_auto', 'he_by_script', pattern"));
+    }
+
+    #[test]
+    fn check_rule_ids_are_quoted() {
+        let sql = with_provenance("update ext.names n set lang_code = 'en'", "lex_auto", "o'neill rule", "null");
+        assert!(sql.contains("'lex_auto', 'o''neill rule', pattern"));
+    }
+
+    #[test]
+    fn check_dry_run_line() {
+        let samples = vec!["Kyoto Daigaku".to_string(), "Nihon Kenkyujo".to_string()];
+        assert_eq!(dry_run_line("lex_auto", "ja", 1756, Some(&1700), &samples),
+                   "lex_auto     ja       1756 names (currently 1700), e.g. 'Kyoto Daigaku', 'Nihon Kenkyujo'");
+        assert_eq!(dry_run_line("cm_brand", "cm", 12, None, &samples[..1]),
+                   "cm_brand     cm         12 names (currently none), e.g. 'Kyoto Daigaku'");
+    }
+}
@@ -0,0 +1,377 @@
+/***************************************************************************
+ * Language coding by location. The languages used in each country, or in
+ * particular subdivisions of a country, are held in a toml file
+ * (region_langs.toml) in the data folder, written from the default version
+ * compiled into the program if not already present (other than in a dry
+ * run). As with the language rules, a warning is given if that file is
+ * from an older version of the defaults. Each entry can be restricted to
+ * names in particular scripts, so that for instance only the Tamil script
+ * names of Indian orgs are coded as Tamil. For each location of an org
+ * (from ext.orgs and ext.locations) the most specific matching entry is
+ * found - a subdivision entry taking precedence over a country entry - and
+ * if all the entries found for a name give the same single language it is
+ * applied, with 'region_auto' as the lang_source and the regions involved
+ * recorded as the provenance pattern. Otherwise, as in multilingual
+ * regions such as Brussels, the name is left uncoded. Names like any of
+ * the file's exclude patterns (the English words excluded from the lexicon
+ * rules) are also left uncoded, as are acronyms.
+ ***************************************************************************/
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use sqlx::PgConnection;
+use log::{info, warn};
+use crate::AppError;
+use crate::import::copy_writer::{copy_rows_on_conn, CopyRows, CopyWriter};
+use super::provenance::with_provenance;
+use super::lang_rules::like_any;
+
+
+pub const REGIONS_FILE_NAME: &str = "region_langs.toml";
+pub const REGION_STEP: &str = "region_auto";
+const REGION_RULE: &str = "region_langs";
+const DEFAULT_REGIONS: &str = include_str!("region_langs.toml");
+const DEFAULT_REGIONS_VERSION: u32 = 2;     // must match the version in region_langs.toml
+
+
+#[derive(Debug, Deserialize)]
+pub struct RegionLangsFile {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub regions: Vec<RegionLang>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegionLang {
+    pub country: String,
+    #[serde(default)]
+    pub subdivisions: Vec<String>,
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    #[serde(default)]
+    pub exclude_scripts: Vec<String>,
+    pub lang_codes: Vec<String>,
+}
+
+impl RegionLang {
+    fn covers_script(&self, script_code: &str) -> bool {
+        (self.scripts.is_empty() || self.scripts.iter().any(|s| s == script_code))
+            && !self.exclude_scripts.iter().any(|s| s == script_code)
+    }
+}
+
+
+// An org location, as a country code and (if known) a subdivision code.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Region {
+    pub country: String,
+    pub subdivision: Option<String>,
+}
+
+impl Region {
+    fn label(&self) -> String {
+        match &self.subdivision {
+            Some(s) => format!("{}-{}", self.country, s),
+            None => self.country.clone(),
+        }
+    }
+}
+
+
+#[derive(Debug, PartialEq)]
+pub enum RegionResult {
+    Coded(String, String),     // lang code, regions involved
+    Excluded,                  // would be coded, but like an exclude pattern
+    Multilingual,
+    NoEntry,
+}
+
+
+pub fn parse_regions(regions_string: &str, source: &str) -> Result<RegionLangsFile, AppError> {
+
+    let regions_file: RegionLangsFile = toml::from_str(regions_string)
+        .map_err(|e| AppError::RulesError(format!("Unable to parse region languages in {}.", source), e.to_string()))?;
+
+    for region in &regions_file.regions {
+        if region.lang_codes.is_empty() || region.lang_codes.iter().any(|c| c.trim().is_empty()) {
+            return Err(AppError::RulesError(format!("Region language entry for '{}' in {} has a missing lang code.", region.country, source),
+                                            "Every entry must list at least one language code.".to_string()));
+        }
+    }
+    Ok(regions_file)
+}
+
+
+pub fn get_regions(data_folder: &Path, write_defaults: bool) -> Result<RegionLangsFile, AppError> {
+
+    let regions_path = data_folder.join(REGIONS_FILE_NAME);
+    if !regions_path.exists() {
+        if !write_defaults {
+            info!("No {} in the data folder - the default region languages are used, but not written", REGIONS_FILE_NAME);
+            return parse_regions(DEFAULT_REGIONS, "default regions");
+        }
+        fs::write(&regions_path, DEFAULT_REGIONS)
+            .map_err(|e| AppError::IoWriteErrorWithPath(e, regions_path.clone()))?;
+        info!("Default region languages written to {}", regions_path.display());
+    }
+
+    let regions_string = fs::read_to_string(&regions_path)
+        .map_err(|e| AppError::IoReadErrorWithPath(e, regions_path.clone()))?;
+    let version = regions_version(&regions_string);
+    if version < DEFAULT_REGIONS_VERSION {
+        warn!("{} is version {} of the region languages, but the program's defaults are version {}. \
+               Changes to the defaults are not in the file - rename or delete it to have the current \
+               defaults written in its place, or merge them in by hand.",
+              regions_path.display(), version, DEFAULT_REGIONS_VERSION);
+    }
+    parse_regions(&regions_string, &regions_path.display().to_string())
+}
+
+fn regions_version(regions_string: &str) -> u32 {
+    toml::from_str::<RegionLangsFile>(regions_string).map(|f| f.version).unwrap_or_default()
+}
+
+
+// The languages for a single location are those of the subdivision entries
+// that list it, or if there are none those of the whole country entries.
+// Locations without any matching entry are ignored.
+
+fn region_langs<'a>(entries: &[&'a RegionLang], region: &Region) -> BTreeSet<&'a str> {
+
+    let in_country = entries.iter().filter(|e| e.country == region.country);
+    let subdiv_entries: Vec<&&RegionLang> = in_country.clone()
+        .filter(|e| region.subdivision.as_ref().is_some_and(|s| e.subdivisions.contains(s)))
+        .collect();
+    let chosen: Vec<&&RegionLang> = if subdiv_entries.is_empty() {
+        in_country.filter(|e| e.subdivisions.is_empty()).collect()
+    } else {
+        subdiv_entries
+    };
+    chosen.iter().flat_map(|e| e.lang_codes.iter().map(|c| c.as_str())).collect()
+}
+
+
+pub fn resolve_lang(regions: &RegionLangsFile, org_regions: &BTreeSet<Region>, script_code: &str,
+                    name_to_match: &str) -> RegionResult {
+
+    let entries: Vec<&RegionLang> = regions.regions.iter().filter(|r| r.covers_script(script_code)).collect();
+
+    let mut langs = BTreeSet::new();
+    let mut labels = Vec::new();
+    for region in org_regions {
+        let found = region_langs(&entries, region);
+        if !found.is_empty() {
+            langs.extend(found);
+            labels.push(region.label());
+        }
+    }
+
+    match langs.len() {
+        0 => RegionResult::NoEntry,
+        1 if like_any(name_to_match, &regions.exclude) => RegionResult::Excluded,
+        1 => RegionResult::Coded(langs.first().unwrap().to_string(), labels.join(", ")),
+        _ => RegionResult::Multilingual,
+    }
+}
+
+
+#[derive(Default)]
+struct RegionCodeVecs {
+    ids: Vec<String>,
+    names: Vec<String>,
+    name_types: Vec<Option<i32>>,
+    lang_codes: Vec<String>,
+    regions: Vec<String>,
+}
+
+impl CopyRows for RegionCodeVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ext.lang_region_codes (id, name, name_type, lang_code, regions) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.names[i]).field(&self.name_types[i])
+             .field(&self.lang_codes[i]).field(&self.regions[i]);
+            w.end_row();
+        }
+    }
+}
+
+
+// id, name, name_type, script_code, name_to_match
+
+type UncodedName = (String, String, Option<i32>, Option<String>, String);
+
+
+async fn fetch_org_regions(conn: &mut PgConnection) -> Result<HashMap<String, BTreeSet<Region>>, AppError> {
+
+    let sql = r#"select id, country_code, csubdiv_code
+            from ext.orgs
+            where country_code is not null
+            union
+            select id, country_code, csubdiv_code
+            from ext.locations
+            where country_code is not null;"#;
+    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(sql)
+        .fetch_all(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut org_regions: HashMap<String, BTreeSet<Region>> = HashMap::new();
+    for (id, country, subdivision) in rows {
+        org_regions.entry(id).or_default().insert(Region { country, subdivision });
+    }
+    Ok(org_regions)
+}
+
+
+pub async fn apply_region_codes(data_folder: &Path, write_defaults: bool, conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let regions = get_regions(data_folder, write_defaults)?;
+    let org_regions = fetch_org_regions(conn).await?;
+
+    let sql = r#"select id, name, name_type, script_code, name_to_match
+            from ext.names
+            where lang_code is null
+            and name_to_match is not null
+            and name_type <> 10;"#;
+    let uncoded: Vec<UncodedName> = sqlx::query_as(sql)
+        .fetch_all(&mut *conn).await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut rv = RegionCodeVecs::default();
+    let mut num_multilingual = 0;
+    let mut num_excluded = 0;
+    let no_regions = BTreeSet::new();
+    for (id, name, name_type, script_code, name_to_match) in uncoded {
+        let this_org_regions = org_regions.get(&id).unwrap_or(&no_regions);
+        match resolve_lang(&regions, this_org_regions, script_code.as_deref().unwrap_or(""), &name_to_match) {
+            RegionResult::Coded(lang_code, labels) => {
+                rv.ids.push(id);
+                rv.names.push(name);
+                rv.name_types.push(name_type);
+                rv.lang_codes.push(lang_code);
+                rv.regions.push(labels);
+            },
+            RegionResult::Excluded => num_excluded += 1,
+            RegionResult::Multilingual => num_multilingual += 1,
+            RegionResult::NoEntry => {},
+        }
+    }
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_region_codes;
+            create table ext.lang_region_codes
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , name_type         int         null
+        , lang_code         varchar     not null
+        , regions           varchar     not null
+    );
+    SET client_min_messages TO NOTICE;"#;
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    copy_rows_on_conn(&rv, conn).await?;
+
+    let sql = "create index lang_region_codes_idx on ext.lang_region_codes(id, name);";
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let update_sql = r#"update ext.names n
+            set lang_code = r.lang_code
+            from ext.lang_region_codes r
+            where n.id = r.id and n.name = r.name
+            and n.name_type is not distinct from r.name_type
+            and n.lang_code is null"#;
+    let sql = with_provenance(update_sql, REGION_STEP, REGION_RULE, "r.regions");
+    let res = sqlx::raw_sql(&sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+
+    info!("{} language codes added from org locations, {} names left uncoded in multilingual regions, \
+           {} left uncoded as matching an exclude pattern", res.rows_affected(), num_multilingual, num_excluded);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lang_rules::parse_rules_file;
+
+    fn region(country: &str, subdivision: Option<&str>) -> Region {
+        Region { country: country.to_string(), subdivision: subdivision.map(String::from) }
+    }
+
+    fn regions(list: &[(&str, Option<&str>)]) -> BTreeSet<Region> {
+        list.iter().map(|(c, s)| region(c, *s)).collect()
+    }
+
+    fn coded(lang: &str, labels: &str) -> RegionResult {
+        RegionResult::Coded(lang.to_string(), labels.to_string())
+    }
+
+    #[test]
+    fn check_default_regions_are_valid() {
+        let entries = parse_regions(DEFAULT_REGIONS, "default regions").unwrap();
+        assert!(entries.regions.iter().any(|e| e.country == "CA" && e.subdivisions == vec!["QC"]));
+        assert_eq!(regions_version(DEFAULT_REGIONS), DEFAULT_REGIONS_VERSION);
+
+        // No country wide entries for Latin script names
+        assert!(!entries.regions.iter().any(|e| e.subdivisions.is_empty() && e.covers_script("Latn")));
+
+        // The exclusions are those of the lexicon rules
+        let rules = parse_rules_file(include_str!("lang_rules.toml"), "default rules").unwrap().rules;
+        let german = rules.iter().find(|r| r.name == "german lexicon").unwrap();
+        assert_eq!(entries.exclude, german.exclude);
+    }
+
+    #[test]
+    fn check_only_subdivision_entries_code_latin_names() {
+        let entries = parse_regions(DEFAULT_REGIONS, "default regions").unwrap();
+
+        assert_eq!(resolve_lang(&entries, &regions(&[("CA", Some("QC"))]), "Latn", "université laval"), coded("fr", "CA-QC"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("CA", Some("AB"))]), "Latn", "université de saint-boniface"), RegionResult::NoEntry);
+        assert_eq!(resolve_lang(&entries, &regions(&[("CA", Some("ON"))]), "Latn", "université d’ottawa"), RegionResult::Multilingual);
+        assert_eq!(resolve_lang(&entries, &regions(&[("ES", Some("CT"))]), "Latn", "hospital clínic de barcelona"), coded("ca", "ES-CT"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("ES", Some("MD"))]), "Latn", "hospital gregorio marañón"), RegionResult::NoEntry);
+        assert_eq!(resolve_lang(&entries, &regions(&[("CH", Some("GE"))]), "Latn", "hôpitaux universitaires de genève"), coded("fr", "CH-GE"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("CH", None)]), "Latn", "eidgenössische technische hochschule"), RegionResult::NoEntry);
+    }
+
+    #[test]
+    fn check_english_names_not_coded_by_region() {
+        let entries = parse_regions(DEFAULT_REGIONS, "default regions").unwrap();
+
+        assert_eq!(resolve_lang(&entries, &regions(&[("CA", Some("QC"))]), "Latn", "canadian space agency"), RegionResult::Excluded);
+        assert_eq!(resolve_lang(&entries, &regions(&[("CA", Some("QC"))]), "Latn", "agence spatiale canadienne"), coded("fr", "CA-QC"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("ES", Some("CT"))]), "Latn", "the barcelona institute of science and technology"), RegionResult::Excluded);
+    }
+
+    #[test]
+    fn check_scripts_and_multiple_locations() {
+        let entries = parse_regions(DEFAULT_REGIONS, "default regions").unwrap();
+
+        assert_eq!(resolve_lang(&entries, &regions(&[("IN", Some("TN"))]), "Taml", "அண்ணா பல்கலைக்கழகம்"), coded("ta", "IN-TN"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("IN", Some("TN"))]), "Latn", "anna university"), RegionResult::NoEntry);
+        assert_eq!(resolve_lang(&entries, &regions(&[("RU", None)]), "Cyrl", "московский университет"), RegionResult::NoEntry);
+
+        // Locations without entries are ignored, but differing languages leave the name uncoded
+        assert_eq!(resolve_lang(&entries, &regions(&[("US", Some("CA")), ("CH", Some("ZH"))]), "Latn", "universität zürich"), coded("de", "CH-ZH"));
+        assert_eq!(resolve_lang(&entries, &regions(&[("CH", Some("ZH")), ("CH", Some("GE"))]), "Latn", "universität zürich"), RegionResult::Multilingual);
+    }
+
+    #[test]
+    fn check_entries_without_lang_codes_are_rejected() {
+        let no_langs = r#"
+            [[regions]]
+            country = "CH"
+            lang_codes = []
+        "#;
+        assert!(matches!(parse_regions(no_langs, "test"), Err(AppError::RulesError(_, _))));
+    }
+}
@@ -0,0 +1,120 @@
+/***************************************************************************
+ * Conflict analysis for the language rules. Because each rule only codes
+ * names that do not yet have a lang code, a name matching rules for two
+ * different languages is coded by whichever is applied first, i.e. by the
+ * rule with the higher priority. Before the rules are applied every name
+ * matching each rule is recorded in ext.lang_rule_matches. Afterwards the
+ * names that match rules for more than one language are listed in
+ * ext.lang_rule_conflicts, with the languages and rules involved and the
+ * code actually applied, and the number of such names is logged for each
+ * pair of languages, so that rule priorities can be set deliberately.
+ ***************************************************************************/
+
+use sqlx::PgConnection;
+use log::{info, warn};
+use crate::AppError;
+use super::lang_rules::{LangRule, rule_conditions, bind_rule};
+
+
+pub async fn record_rule_matches(rules: &[LangRule], conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_rule_matches;
+            create table ext.lang_rule_matches
+    (
+          id                varchar     not null
+        , name              varchar     not null
+        , name_type         int         null
+        , lang_code         varchar     not null
+        , rule              varchar     not null
+        , priority          int         not null
+    );
+    SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    for rule in rules {
+        let sql = format!(r#"insert into ext.lang_rule_matches (id, name, name_type, lang_code, rule, priority)
+                select n.id, n.name, n.name_type, $1, $8, $9
+                from ext.names n
+                where {};"#, rule_conditions(rule));
+        bind_rule(sqlx::query(&sql), rule)
+            .bind(&rule.name)
+            .bind(rule.priority)
+            .execute(&mut *conn).await
+            .map_err(|e| AppError::SqlxError(e, sql))?;
+    }
+
+    let sql = "create index lang_rule_matches_idx on ext.lang_rule_matches(id, name);";
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(())
+}
+
+
+pub async fn report_conflicts(conn: &mut PgConnection) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists ext.lang_rule_conflicts;
+            create table ext.lang_rule_conflicts as
+            select m.id, m.name, m.name_type,
+            string_agg(distinct m.lang_code, ', ') as lang_codes,
+            string_agg(distinct m.rule, ', ') as rules,
+            min(n.lang_code) as applied_lang_code
+            from ext.lang_rule_matches m
+            inner join ext.names n
+            on m.id = n.id and m.name = n.name
+            and m.name_type is not distinct from n.name_type
+            group by m.id, m.name, m.name_type
+            having count(distinct m.lang_code) > 1;
+            SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let sql = "select count(*) from ext.lang_rule_conflicts;";
+    let num_conflicts: i64 = sqlx::query_scalar(sql).fetch_one(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if num_conflicts == 0 {
+        info!("No names match language rules for more than one language");
+        return Ok(());
+    }
+
+    let sql = r#"select a.lang_code, b.lang_code,
+            count(distinct (a.id, a.name, a.name_type)) as num,
+            string_agg(distinct a.rule, ', ') as rules_a,
+            string_agg(distinct b.rule, ', ') as rules_b
+            from ext.lang_rule_matches a
+            inner join ext.lang_rule_matches b
+            on a.id = b.id and a.name = b.name
+            and a.name_type is not distinct from b.name_type
+            and a.lang_code < b.lang_code
+            group by a.lang_code, b.lang_code
+            order by num desc, a.lang_code, b.lang_code;"#;
+    let pairs: Vec<(String, String, i64, String, String)> = sqlx::query_as(sql).fetch_all(&mut *conn)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    warn!("{} names match language rules for more than one language (listed in ext.lang_rule_conflicts)", num_conflicts);
+    for (lang_a, lang_b, num, rules_a, rules_b) in pairs {
+        warn!("{}", conflict_line(&lang_a, &lang_b, num, &rules_a, &rules_b));
+    }
+    Ok(())
+}
+
+
+fn conflict_line(lang_a: &str, lang_b: &str, num: i64, rules_a: &str, rules_b: &str) -> String {
+    format!("{:>8} names match rules for both {} ({}) and {} ({})", num, lang_a, rules_a, lang_b, rules_b)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_conflict_line() {
+        assert_eq!(conflict_line("en", "hi", 12, "indian english centres", "hindi"),
+                   "      12 names match rules for both en (indian english centres) and hi (hindi)");
+    }
+}
@@ -0,0 +1,180 @@
+/***************************************************************************
+ * Bulk loading of tables using the postgres COPY protocol. Rows are written
+ * into a buffer in COPY's text format (tab separated fields, '\N' for nulls,
+ * with backslashes, tabs and line breaks escaped) and the whole buffer is
+ * then sent to the server in a single 'copy ... from stdin' operation.
+ * Each target table is represented by a type implementing CopyRows, which
+ * provides the copy statement (listing the columns) and writes each row's
+ * fields, in the same order, using the typed CopyField implementations.
+ ***************************************************************************/
+
+use sqlx::{Pool, Postgres, PgConnection};
+use crate::AppError;
+
+
+pub trait CopyField {
+    fn write_field(&self, out: &mut String);
+}
+
+impl CopyField for str {
+    fn write_field(&self, out: &mut String) {
+        for c in self.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\t' => out.push_str("\\t"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                _ => out.push(c),
+            }
+        }
+    }
+}
+
+impl CopyField for String {
+    fn write_field(&self, out: &mut String) {
+        self.as_str().write_field(out);
+    }
+}
+
+impl CopyField for i32 {
+    fn write_field(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+}
+
+impl CopyField for i64 {
+    fn write_field(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+}
+
+impl CopyField for f32 {
+    fn write_field(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+}
+
+impl CopyField for f64 {
+    fn write_field(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+}
+
+impl CopyField for bool {
+    fn write_field(&self, out: &mut String) {
+        out.push(if *self { 't' } else { 'f' });
+    }
+}
+
+impl<T: CopyField> CopyField for Option<T> {
+    fn write_field(&self, out: &mut String) {
+        match self {
+            Some(v) => v.write_field(out),
+            None => out.push_str("\\N"),
+        }
+    }
+}
+
+
+#[derive(Default)]
+pub struct CopyWriter {
+    buffer: String,
+    row_started: bool,
+    row_count: u64,
+}
+
+impl CopyWriter {
+
+    pub fn field<T: CopyField + ?Sized>(&mut self, value: &T) -> &mut Self {
+        if self.row_started {
+            self.buffer.push('\t');
+        }
+        value.write_field(&mut self.buffer);
+        self.row_started = true;
+        self
+    }
+
+    pub fn end_row(&mut self) {
+        self.buffer.push('\n');
+        self.row_started = false;
+        self.row_count += 1;
+    }
+
+    pub fn row_count(&self) -> u64 {
+        self.row_count
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer.as_bytes()
+    }
+}
+
+
+pub trait CopyRows {
+    fn copy_statement(&self) -> &'static str;
+    fn write_rows(&self, w: &mut CopyWriter);
+}
+
+
+pub async fn copy_rows<T: CopyRows>(rows: &T, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let sql = rows.copy_statement();
+    let mut conn = pool.acquire().await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    copy_rows_on_conn(rows, &mut conn).await
+}
+
+
+// For use within a transaction, or wherever a connection is already held.
+
+pub async fn copy_rows_on_conn<T: CopyRows>(rows: &T, conn: &mut PgConnection) -> Result<u64, AppError> {
+
+    let mut w = CopyWriter::default();
+    rows.write_rows(&mut w);
+    if w.row_count() == 0 {
+        return Ok(0);
+    }
+
+    let sql = rows.copy_statement();
+    let mut copy_in = conn.copy_in_raw(sql).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    copy_in.send(w.as_bytes()).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    copy_in.finish().await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_fields_are_tab_separated() {
+        let mut w = CopyWriter::default();
+        w.field("04ttjf776").field(&5).field(&true).field(&Some(1.5f32));
+        w.end_row();
+        w.field("02abcd123").field(&10).field(&false).field(&None::<f32>);
+        w.end_row();
+        assert_eq!(w.row_count(), 2);
+        assert_eq!(std::str::from_utf8(w.as_bytes()).unwrap(),
+                   "04ttjf776\t5\tt\t1.5\n02abcd123\t10\tf\t\\N\n");
+    }
+
+    #[test]
+    fn check_special_characters_are_escaped() {
+        let mut w = CopyWriter::default();
+        w.field("a\tb").field("c\\d").field("e\nf\r");
+        w.end_row();
+        assert_eq!(std::str::from_utf8(w.as_bytes()).unwrap(),
+                   "a\\tb\tc\\\\d\te\\nf\\r\n");
+    }
+
+    #[test]
+    fn check_optional_strings() {
+        let mut w = CopyWriter::default();
+        w.field(&Some("fr".to_string())).field(&None::<String>);
+        w.end_row();
+        assert_eq!(std::str::from_utf8(w.as_bytes()).unwrap(), "fr\t\\N\n");
+    }
+}
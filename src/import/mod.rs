@@ -1,12 +1,131 @@
+/***************************************************************************
+ * The import module reads a ROR json file, deserialises each record
+ * and stores the data in the tables of the ror schema, one table for each
+ * of the repeating components of a ROR record (names, types, locations,
+ * relationships, external ids, links and domains) as well as tables for
+ * the core and admin data. The ror schema tables are recreated each time
+ * an import takes place. Files using the older v1 schema are mapped into
+ * the same tables, with the same codes, as v2 files. The csv version of
+ * the v2 data, included in each ROR release, can be imported instead.
+ ***************************************************************************/
 
-// use log::{info, error};
-// use std::path::PathBuf;
-//use std::fs;
-//use sqlx::{Pool, Postgres};
-//use crate::AppError;
+pub mod copy_writer;
+mod ror_csv_reader;
+mod ror_json_models;
+mod ror_json_models_v1;
+mod ror_data_vectors;
+mod ror_json_reader;
+mod ror_rejects;
+mod ror_tables;
 
- 
-  
-  
-  
+use log::{info, warn, error};
+use std::path::{Path, PathBuf};
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+use ror_data_vectors::DataVecs;
+use ror_rejects::RejectsFile;
+use tokio::sync::mpsc;
 
+
+// Number of records in each batch sent to the database, and the number of
+// batches that can be waiting to be stored while the next is assembled.
+
+const BATCH_SIZE: usize = 5000;
+const BATCHES_IN_FLIGHT: usize = 2;
+
+
+pub async fn create_ror_tables(pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    match ror_tables::create_tables(pool).await {
+        Ok(()) => info!("Tables created for ror schema"),
+        Err(e) => {
+            error!("An error occured while creating the ror schema tables: {}", e);
+            return Err(e)
+            },
+    };
+    Ok(())
+}
+
+
+pub async fn import_data(data_folder : &Path, output_folder : &Path, source_file_name: &str, 
+                         pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let source_file_path: PathBuf = data_folder.join(source_file_name);
+
+    // The file is parsed on a blocking thread, which streams batches of records
+    // back through a bounded channel. Each batch has been flattened into a set 
+    // of vectors, one for each column of each table.
+
+    // Records that do not match the ROR schema are listed in a rejects file.
+
+    let is_csv = ror_csv_reader::is_csv_file(&source_file_path);
+    let mut rejects = RejectsFile::new(output_folder, source_file_name);
+    let (tx, mut rx) = mpsc::channel::<DataVecs>(BATCHES_IN_FLIGHT);
+    let reader = tokio::task::spawn_blocking(move || {
+        let res = if is_csv {
+            ror_csv_reader::read_records(&source_file_path, BATCH_SIZE, tx, &mut rejects)
+        }
+        else {
+            ror_json_reader::read_records(&source_file_path, BATCH_SIZE, tx, &mut rejects)
+        };
+        (res, rejects)
+    });
+
+    let mut n = 0;
+    while let Some(dv) = rx.recv().await {
+        dv.store_data(pool).await?;
+        n += dv.record_count();
+        info!("{} records imported", n);
+    }
+
+    let (res, mut rejects) = reader.await
+        .map_err(|e| AppError::ImportError("Source file reader did not complete".to_string(), e.to_string()))?;
+    rejects.finish()?;
+    let records_read = res?;
+
+    info!("{} records read and imported from {}", records_read, source_file_name);
+    if rejects.count() > 0 {
+        warn!("{} records rejected as not matching the ROR schema - see {}", rejects.count(), rejects.path().display());
+    }
+    else {
+        info!("No records rejected");
+    }
+
+    if is_csv {
+        let n = ror_tables::add_related_labels(pool).await?;
+        info!("{} relationship labels added from the names table", n);
+    }
+
+    report_table_counts(pool).await?;
+
+    Ok(())
+}
+
+
+pub async fn record_version(data_version: &str, data_date: &str, source_file_name: &str,
+                            pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    ror_tables::store_version_details(data_version, data_date, source_file_name, pool).await?;
+    info!("Version {} ({}) recorded as the current ror data", data_version, data_date);
+    Ok(())
+}
+
+
+pub async fn get_current_version(pool : &Pool<Postgres>) -> Result<Option<(String, String)>, AppError>
+{
+    ror_tables::fetch_latest_version(pool).await
+}
+
+
+async fn report_table_counts(pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    let tables = ["core_data", "admin_data", "names", "type", "locations",
+                  "relationships", "external_ids", "links", "domains"];
+    for t in tables {
+        let sql = format!("select count(*) from ror.{}", t);
+        let res: i64 = sqlx::query_scalar(&sql).fetch_one(pool).await
+                    .map_err(|e| AppError::SqlxError(e, sql.clone()))?;
+        info!("{} records in ror.{}", res, t);
+    }
+    Ok(())
+}
@@ -0,0 +1,471 @@
+/***************************************************************************
+ * Each ROR release also includes a flattened csv version of the v2 data,
+ * with one row per organisation and one column per json field, the column
+ * names being the json paths (e.g. 'locations.geonames_details.lat'). Any
+ * field that can have several values holds them in a single column,
+ * separated by semi-colons, and the names carry their language code as a
+ * '*' suffix (e.g. 'Université de Paris*fr'). Each row is parsed back into
+ * a RorRecord, so that the data can be batched and stored in exactly the
+ * same way as the json data. Rows that cannot be parsed are written to the
+ * rejects file, with the column name in place of the json path, as are
+ * rows that cannot be read at all (with '(row)' in place of the path).
+ * The csv has no labels for related organisations - these are added from
+ * the names table once the import is complete.
+ ***************************************************************************/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use csv::{ReaderBuilder, StringRecord};
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use tokio::sync::mpsc::Sender;
+use crate::AppError;
+use super::ror_json_models::{RorRecord, Admin, DateAndSchema, ExternalId, GeonamesDetails, Link,
+                             Location, Name, Relationship, NameType, IdType, LinkType, RelType};
+use super::ror_data_vectors::DataVecs;
+use super::ror_rejects::RejectsFile;
+
+
+const CSV_COLUMNS: [&str; 34] = [
+    "id", "admin.created.date", "admin.created.schema_version", "admin.last_modified.date",
+    "admin.last_modified.schema_version", "domains", "established",
+    "external_ids.type.fundref.all", "external_ids.type.fundref.preferred",
+    "external_ids.type.grid.all", "external_ids.type.grid.preferred",
+    "external_ids.type.isni.all", "external_ids.type.isni.preferred",
+    "external_ids.type.wikidata.all", "external_ids.type.wikidata.preferred",
+    "links.type.website", "links.type.wikipedia",
+    "locations.geonames_details.continent_code", "locations.geonames_details.continent_name",
+    "locations.geonames_details.country_code", "locations.geonames_details.country_name",
+    "locations.geonames_details.country_subdivision_code", "locations.geonames_details.country_subdivision_name",
+    "locations.geonames_details.lat", "locations.geonames_details.lng", "locations.geonames_details.name",
+    "locations.geonames_id", "names.types.acronym", "names.types.alias", "names.types.label",
+    "names.types.ror_display", "relationships", "status", "types",
+];
+
+// A row level error gives the column name and the error message.
+
+type FieldResult<T> = Result<T, (&'static str, String)>;
+
+
+pub fn is_csv_file(file_path: &Path) -> bool {
+    match file_path.extension() {
+        Some(ext) => ext.eq_ignore_ascii_case("csv"),
+        None => false,
+    }
+}
+
+
+pub fn read_records(file_path: &Path, batch_size: usize, tx: Sender<DataVecs>,
+                    rejects: &mut RejectsFile) -> Result<usize, AppError> {
+
+    let file = File::open(file_path)
+                .map_err(|e| AppError::IoReadErrorWithPath(e, file_path.to_owned()))?;
+    stream_records(BufReader::new(file), batch_size, tx, rejects)
+}
+
+
+fn stream_records<R: Read>(rdr: R, batch_size: usize, tx: Sender<DataVecs>,
+                           rejects: &mut RejectsFile) -> Result<usize, AppError> {
+
+    let mut csv_rdr = ReaderBuilder::new().from_reader(rdr);
+    let headers = csv_rdr.headers()
+        .map_err(|e| AppError::ImportError("Unable to read csv header row".to_string(), e.to_string()))?;
+    let cols = CsvColumns::from_headers(headers)?;
+
+    let mut record_count = 0;
+    let mut dv = DataVecs::default();
+
+    for result in csv_rdr.records() {
+
+        // A row that cannot be read (e.g. with the wrong number of fields) is
+        // rejected, as for a row that cannot be parsed. Only io errors, after
+        // which no further rows could be read, stop the import.
+
+        let row = match result {
+            Ok(row) => row,
+            Err(e) if e.is_io_error() => {
+                return Err(AppError::ImportError("Unable to read csv row".to_string(), e.to_string()));
+            },
+            Err(e) => {
+                rejects.add("(no id)", "(row)", &e.to_string())?;
+                continue;
+            }
+        };
+        match parse_row(&cols, &row) {
+            Ok(r) => {
+                dv.add_record(&r);
+                record_count += 1;
+            },
+            Err((column, error)) => {
+                rejects.add(cols.value(&row, "id"), column, &error)?;
+            }
+        }
+        if dv.record_count() == batch_size {
+            let full_batch = std::mem::take(&mut dv);
+            tx.blocking_send(full_batch)
+                .map_err(|_| AppError::ImportError("Batch receiver closed before end of file".to_string(), "".to_string()))?;
+        }
+    }
+
+    if dv.record_count() > 0 {
+        tx.blocking_send(dv)
+            .map_err(|_| AppError::ImportError("Batch receiver closed before end of file".to_string(), "".to_string()))?;
+    }
+
+    Ok(record_count)
+}
+
+
+struct CsvColumns {
+    positions: HashMap<&'static str, usize>,
+}
+
+impl CsvColumns {
+
+    fn from_headers(headers: &StringRecord) -> Result<Self, AppError> {
+        let mut positions = HashMap::new();
+        for col in CSV_COLUMNS {
+            match headers.iter().position(|h| h.trim() == col) {
+                Some(i) => { positions.insert(col, i); },
+                None => return Err(AppError::ImportError(format!("Column '{}' not found in csv file", col),
+                                    "Only the schema v2 csv file from a ROR release can be imported".to_string())),
+            }
+        }
+        Ok(CsvColumns { positions })
+    }
+
+    fn value<'r>(&self, row: &'r StringRecord, col: &str) -> &'r str {
+        self.positions.get(col).and_then(|i| row.get(*i)).unwrap_or("").trim()
+    }
+}
+
+
+fn parse_row(cols: &CsvColumns, row: &StringRecord) -> FieldResult<RorRecord> {
+
+    let id = cols.value(row, "id");
+    if id.is_empty() {
+        return Err(("id", "missing value".to_string()));
+    }
+
+    let admin = Admin {
+        created: DateAndSchema {
+            date: required(cols, row, "admin.created.date")?,
+            schema_version: required(cols, row, "admin.created.schema_version")?,
+        },
+        last_modified: DateAndSchema {
+            date: required(cols, row, "admin.last_modified.date")?,
+            schema_version: required(cols, row, "admin.last_modified.schema_version")?,
+        },
+    };
+
+    let established = match cols.value(row, "established") {
+        "" => None,
+        v => Some(v.parse::<i32>().map_err(|e| ("established", e.to_string()))?),
+    };
+
+    Ok(RorRecord {
+        id: id.to_string(),
+        admin: Some(admin),
+        domains: split_values(cols.value(row, "domains")).into_iter().map(|d| d.to_string()).collect(),
+        established,
+        external_ids: parse_external_ids(cols, row),
+        links: parse_links(cols, row),
+        locations: parse_locations(cols, row)?,
+        names: parse_names(cols, row),
+        relationships: parse_relationships(cols.value(row, "relationships"))?,
+        status: parse_code(cols.value(row, "status")).map_err(|e| ("status", e))?,
+        types: cols.value(row, "types").split([';', ',']).map(|t| t.trim()).filter(|t| !t.is_empty())
+                   .map(parse_code).collect::<Result<_, _>>().map_err(|e| ("types", e))?,
+    })
+}
+
+
+fn required(cols: &CsvColumns, row: &StringRecord, col: &'static str) -> FieldResult<String> {
+    match cols.value(row, col) {
+        "" => Err((col, "missing value".to_string())),
+        v => Ok(v.to_string()),
+    }
+}
+
+
+// Enumerated values are parsed with the same serde names as in the json.
+
+fn parse_code<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> = value.into_deserializer();
+    T::deserialize(deserializer).map_err(|e| e.to_string())
+}
+
+
+fn split_values(value: &str) -> Vec<&str> {
+    value.split(';').map(|v| v.trim()).filter(|v| !v.is_empty()).collect()
+}
+
+
+// Location columns are kept aligned by position, so empty values are retained.
+
+fn split_aligned(value: &str) -> Vec<&str> {
+    if value.is_empty() {
+        Vec::new()
+    }
+    else {
+        value.split(';').map(|v| v.trim()).collect()
+    }
+}
+
+
+fn split_lang(value: &str) -> (String, Option<String>) {
+    if let Some((name, lang)) = value.rsplit_once('*')
+        && (2..=3).contains(&lang.len()) && lang.chars().all(|c| c.is_ascii_lowercase()) {
+        return (name.trim().to_string(), Some(lang.to_string()));
+    }
+    (value.to_string(), None)
+}
+
+
+fn parse_names(cols: &CsvColumns, row: &StringRecord) -> Vec<Name> {
+
+    let mut names = Vec::new();
+    let name_cols = [("names.types.label", NameType::Label), ("names.types.alias", NameType::Alias),
+                     ("names.types.acronym", NameType::Acronym)];
+    for (col, name_type) in name_cols {
+        for v in split_values(cols.value(row, col)) {
+            let (value, lang) = split_lang(v);
+            names.push(Name { value, types: vec![name_type], lang });
+        }
+    }
+
+    // The ror display name is normally also listed as a label.
+
+    for v in split_values(cols.value(row, "names.types.ror_display")) {
+        let (value, lang) = split_lang(v);
+        match names.iter_mut().find(|n| n.value == value && n.types.contains(&NameType::Label)) {
+            Some(n) => n.types.insert(0, NameType::RorDisplay),
+            None => names.insert(0, Name { value, types: vec![NameType::RorDisplay], lang }),
+        }
+    }
+    names
+}
+
+
+fn parse_external_ids(cols: &CsvColumns, row: &StringRecord) -> Vec<ExternalId> {
+
+    let id_cols = [("external_ids.type.fundref.all", "external_ids.type.fundref.preferred", IdType::Fundref),
+                   ("external_ids.type.grid.all", "external_ids.type.grid.preferred", IdType::Grid),
+                   ("external_ids.type.isni.all", "external_ids.type.isni.preferred", IdType::Isni),
+                   ("external_ids.type.wikidata.all", "external_ids.type.wikidata.preferred", IdType::Wikidata)];
+    let mut external_ids = Vec::new();
+    for (all_col, pref_col, id_type) in id_cols {
+        let all: Vec<String> = split_values(cols.value(row, all_col)).into_iter().map(|v| v.to_string()).collect();
+        if !all.is_empty() {
+            let preferred = match cols.value(row, pref_col) {
+                "" => None,
+                v => Some(v.to_string()),
+            };
+            external_ids.push(ExternalId { id_type, all, preferred });
+        }
+    }
+    external_ids
+}
+
+
+fn parse_links(cols: &CsvColumns, row: &StringRecord) -> Vec<Link> {
+
+    let mut links = Vec::new();
+    for (col, link_type) in [("links.type.website", LinkType::Website), ("links.type.wikipedia", LinkType::Wikipedia)] {
+        for v in split_values(cols.value(row, col)) {
+            links.push(Link { link_type, value: v.to_string() });
+        }
+    }
+    links
+}
+
+
+fn parse_locations(cols: &CsvColumns, row: &StringRecord) -> FieldResult<Vec<Location>> {
+
+    let col_values = |col: &str| split_aligned(cols.value(row, col));
+    let nth_string = |values: &Vec<&str>, i: usize| values.get(i).filter(|v| !v.is_empty()).map(|v| v.to_string());
+    let nth_float = |values: &Vec<&str>, i: usize, col: &'static str| -> FieldResult<Option<f32>> {
+        match values.get(i).filter(|v| !v.is_empty()) {
+            Some(v) => v.parse::<f32>().map(Some).map_err(|e| (col, e.to_string())),
+            None => Ok(None),
+        }
+    };
+
+    let geonames_ids = col_values("locations.geonames_id");
+    let cont_codes = col_values("locations.geonames_details.continent_code");
+    let cont_names = col_values("locations.geonames_details.continent_name");
+    let country_codes = col_values("locations.geonames_details.country_code");
+    let country_names = col_values("locations.geonames_details.country_name");
+    let csubdiv_codes = col_values("locations.geonames_details.country_subdivision_code");
+    let csubdiv_names = col_values("locations.geonames_details.country_subdivision_name");
+    let lats = col_values("locations.geonames_details.lat");
+    let lngs = col_values("locations.geonames_details.lng");
+    let names = col_values("locations.geonames_details.name");
+
+    let mut locations = Vec::new();
+    for (i, geonames_id) in geonames_ids.iter().enumerate() {
+        locations.push(Location {
+            geonames_id: geonames_id.parse::<i32>().map_err(|e| ("locations.geonames_id", e.to_string()))?,
+            geonames_details: GeonamesDetails {
+                continent_code: nth_string(&cont_codes, i),
+                continent_name: nth_string(&cont_names, i),
+                country_code: nth_string(&country_codes, i),
+                country_name: nth_string(&country_names, i),
+                country_subdivision_code: nth_string(&csubdiv_codes, i),
+                country_subdivision_name: nth_string(&csubdiv_names, i),
+                lat: nth_float(&lats, i, "locations.geonames_details.lat")?,
+                lng: nth_float(&lngs, i, "locations.geonames_details.lng")?,
+                name: nth_string(&names, i),
+            },
+        });
+    }
+    Ok(locations)
+}
+
+
+// Relationships are listed as 'type: ror id' pairs, e.g. 'parent: https://ror.org/01abc;
+// related: https://ror.org/02def, https://ror.org/03ghi', where an id without its own
+// type takes the type that precedes it.
+
+fn parse_relationships(value: &str) -> FieldResult<Vec<Relationship>> {
+
+    let mut relationships = Vec::new();
+    let mut rel_type: Option<RelType> = None;
+    for item in value.split([';', ',']).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        let id = match item.split_once(": ") {
+            Some((t, id)) => {
+                rel_type = Some(parse_code(&t.trim().to_lowercase()).map_err(|e| ("relationships", e))?);
+                id.trim()
+            },
+            None => item,
+        };
+        match rel_type {
+            Some(rel_type) => relationships.push(Relationship { rel_type, label: None, id: id.to_string() }),
+            None => return Err(("relationships", format!("no relationship type given for {}", id))),
+        }
+    }
+    Ok(relationships)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ror_json_models::{OrgStatus, OrgType};
+    use tokio::sync::mpsc;
+
+    fn test_csv(rows: &[Vec<(&str, &str)>]) -> String {
+        let mut csv = CSV_COLUMNS.join(",") + "\n";
+        for row in rows {
+            let values: Vec<String> = CSV_COLUMNS.iter().map(|c| {
+                let v = row.iter().find(|(k, _)| k == c).map(|(_, v)| *v).unwrap_or("");
+                format!("\"{}\"", v)
+            }).collect();
+            csv = csv + &values.join(",") + "\n";
+        }
+        csv
+    }
+
+    fn test_row(id: &'static str) -> Vec<(&'static str, &'static str)> {
+        vec![("id", id), ("admin.created.date", "2020-01-01"), ("admin.created.schema_version", "1.0"),
+             ("admin.last_modified.date", "2025-01-01"), ("admin.last_modified.schema_version", "2.1"),
+             ("status", "active"), ("types", "education; funder"), ("established", "1899"),
+             ("names.types.ror_display", "Universität Wien"),
+             ("names.types.label", "Universität Wien*de; University of Vienna*en"),
+             ("names.types.acronym", "UW"),
+             ("external_ids.type.grid.all", "grid.10420.37"), ("external_ids.type.grid.preferred", "grid.10420.37"),
+             ("external_ids.type.isni.all", "0000 0001 2286 1424; 0000 0004 0000 0001"),
+             ("links.type.website", "https://www.univie.ac.at"), ("domains", "univie.ac.at"),
+             ("locations.geonames_id", "2761369"), ("locations.geonames_details.country_code", "AT"),
+             ("locations.geonames_details.country_subdivision_code", "9"),
+             ("locations.geonames_details.lat", "48.20849"), ("locations.geonames_details.lng", "16.37208"),
+             ("locations.geonames_details.name", "Vienna"),
+             ("relationships", "related: https://ror.org/05n3x4p02, https://ror.org/03prydq77; child: https://ror.org/02xy3s404")]
+    }
+
+    #[test]
+    fn check_csv_row_is_parsed() {
+        let csv = test_csv(&[test_row("https://ror.org/03prkd923")]);
+        let mut rdr = ReaderBuilder::new().from_reader(csv.as_bytes());
+        let cols = CsvColumns::from_headers(rdr.headers().unwrap()).unwrap();
+        let row = rdr.records().next().unwrap().unwrap();
+        let r = parse_row(&cols, &row).unwrap();
+
+        assert_eq!(r.status, OrgStatus::Active);
+        assert_eq!(r.types, vec![OrgType::Education, OrgType::Funder]);
+        assert_eq!(r.established, Some(1899));
+
+        let names: Vec<(&str, Vec<NameType>, Option<&str>)> = r.names.iter()
+            .map(|n| (n.value.as_str(), n.types.clone(), n.lang.as_deref())).collect();
+        assert_eq!(names, vec![
+            ("Universität Wien", vec![NameType::RorDisplay, NameType::Label], Some("de")),
+            ("University of Vienna", vec![NameType::Label], Some("en")),
+            ("UW", vec![NameType::Acronym], None),
+        ]);
+
+        assert_eq!(r.external_ids.len(), 2);
+        assert_eq!(r.external_ids[1].id_type, IdType::Isni);
+        assert_eq!(r.external_ids[1].all.len(), 2);
+        assert_eq!(r.locations[0].geonames_id, 2761369);
+        assert_eq!(r.locations[0].geonames_details.lat, Some(48.20849));
+        assert_eq!(r.relationships.len(), 3);
+        assert_eq!(r.relationships[1].rel_type, RelType::Related);
+        assert_eq!(r.relationships[2].rel_type, RelType::Child);
+    }
+
+    #[test]
+    fn check_name_lang_suffix() {
+        assert_eq!(split_lang("Université de Paris*fr"), ("Université de Paris".to_string(), Some("fr".to_string())));
+        assert_eq!(split_lang("Star*Lab"), ("Star*Lab".to_string(), None));
+        assert_eq!(split_lang("UW"), ("UW".to_string(), None));
+    }
+
+    #[test]
+    fn check_invalid_rows_are_rejected() {
+        let mut bad_row = test_row("https://ror.org/000000002");
+        bad_row.retain(|(k, _)| *k != "status");
+        bad_row.push(("status", "defunct"));
+        let csv = test_csv(&[test_row("https://ror.org/000000001"), bad_row, test_row("https://ror.org/000000003")]);
+
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = RejectsFile::new(&std::env::temp_dir(), "mk_org csv test.csv");
+        let res = stream_records(csv.as_bytes(), 10, tx, &mut rejects).unwrap();
+        rejects.finish().unwrap();
+        assert_eq!(res, 2);
+        assert_eq!(rejects.count(), 1);
+        assert_eq!(rx.try_recv().unwrap().record_count(), 2);
+
+        let contents = std::fs::read_to_string(rejects.path()).unwrap();
+        assert!(contents.lines().nth(1).unwrap().starts_with("https://ror.org/000000002,status,"));
+        std::fs::remove_file(rejects.path()).unwrap();
+    }
+
+    #[test]
+    fn check_malformed_rows_are_rejected() {
+        let csv = test_csv(&[test_row("https://ror.org/000000001"), test_row("https://ror.org/000000003")]);
+        let mut lines: Vec<&str> = csv.lines().collect();
+        lines.insert(2, "\"https://ror.org/000000002\",\"2020-01-01\"");
+        let csv = lines.join("\n") + "\n";
+
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = RejectsFile::new(&std::env::temp_dir(), "mk_org csv malformed test.csv");
+        let res = stream_records(csv.as_bytes(), 10, tx, &mut rejects).unwrap();
+        rejects.finish().unwrap();
+        assert_eq!(res, 2);
+        assert_eq!(rejects.count(), 1);
+        assert_eq!(rx.try_recv().unwrap().record_count(), 2);
+
+        let contents = std::fs::read_to_string(rejects.path()).unwrap();
+        assert!(contents.lines().nth(1).unwrap().starts_with("(no id),(row),"));
+        std::fs::remove_file(rejects.path()).unwrap();
+    }
+
+    #[test]
+    fn check_missing_columns_are_reported() {
+        let (tx, _rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = RejectsFile::new(&std::env::temp_dir(), "mk_org csv columns test.csv");
+        let res = stream_records("id,name,types\n".as_bytes(), 10, tx, &mut rejects);
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,371 @@
+/***************************************************************************
+ * The DataVecs struct holds the ROR records of a single batch, flattened
+ * into one set of column vectors per ror schema table. Once a batch has been
+ * accumulated each table's vectors are written out as rows and bulk loaded
+ * using COPY, so that each table receives a single copy operation per batch.
+ * All the copies for a batch run on one connection, within a transaction,
+ * so that a batch is either stored completely or not at all.
+ ***************************************************************************/
+
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+use super::ror_json_models::{RorRecord, NameType};
+use super::copy_writer::{copy_rows_on_conn, CopyRows, CopyWriter};
+
+
+#[derive(Default)]
+pub struct DataVecs {
+    pub core: CoreDataVecs,
+    pub admin: AdminDataVecs,
+    pub names: NameVecs,
+    pub types: TypeVecs,
+    pub locations: LocationVecs,
+    pub relationships: RelationshipVecs,
+    pub external_ids: ExternalIdVecs,
+    pub links: LinkVecs,
+    pub domains: DomainVecs,
+}
+
+#[derive(Default)]
+pub struct CoreDataVecs {
+    pub ids: Vec<String>,
+    pub ror_full_ids: Vec<String>,
+    pub statuses: Vec<i32>,
+    pub estabs: Vec<Option<i32>>,
+}
+
+#[derive(Default)]
+pub struct AdminDataVecs {
+    pub ids: Vec<String>,
+    pub created: Vec<String>,
+    pub cr_schemas: Vec<String>,
+    pub last_modified: Vec<String>,
+    pub lm_schemas: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct NameVecs {
+    pub ids: Vec<String>,
+    pub values: Vec<String>,
+    pub name_types: Vec<i32>,
+    pub is_ror_names: Vec<bool>,
+    pub langs: Vec<Option<String>>,
+}
+
+#[derive(Default)]
+pub struct TypeVecs {
+    pub ids: Vec<String>,
+    pub org_types: Vec<i32>,
+}
+
+#[derive(Default)]
+pub struct LocationVecs {
+    pub ids: Vec<String>,
+    pub geonames_ids: Vec<i32>,
+    pub names: Vec<Option<String>>,
+    pub lats: Vec<Option<f32>>,
+    pub lngs: Vec<Option<f32>>,
+    pub cont_codes: Vec<Option<String>>,
+    pub cont_names: Vec<Option<String>>,
+    pub country_codes: Vec<Option<String>>,
+    pub country_names: Vec<Option<String>>,
+    pub csubdiv_codes: Vec<Option<String>>,
+    pub csubdiv_names: Vec<Option<String>>,
+}
+
+#[derive(Default)]
+pub struct RelationshipVecs {
+    pub ids: Vec<String>,
+    pub rel_types: Vec<i32>,
+    pub related_ids: Vec<String>,
+    pub related_labels: Vec<Option<String>>,
+}
+
+#[derive(Default)]
+pub struct ExternalIdVecs {
+    pub ids: Vec<String>,
+    pub id_types: Vec<i32>,
+    pub id_values: Vec<String>,
+    pub is_preferreds: Vec<bool>,
+}
+
+#[derive(Default)]
+pub struct LinkVecs {
+    pub ids: Vec<String>,
+    pub link_types: Vec<i32>,
+    pub values: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct DomainVecs {
+    pub ids: Vec<String>,
+    pub values: Vec<String>,
+}
+
+
+impl DataVecs {
+
+    pub fn record_count(&self) -> usize {
+        self.core.ids.len()
+    }
+
+    pub fn add_record(&mut self, r: &RorRecord) {
+
+        let id = extract_id(&r.id);
+
+        self.core.ids.push(id.clone());
+        self.core.ror_full_ids.push(r.id.clone());
+        self.core.statuses.push(r.status as i32);
+        self.core.estabs.push(r.established);
+
+        if let Some(admin) = &r.admin {
+            self.admin.ids.push(id.clone());
+            self.admin.created.push(admin.created.date.clone());
+            self.admin.cr_schemas.push(admin.created.schema_version.clone());
+            self.admin.last_modified.push(admin.last_modified.date.clone());
+            self.admin.lm_schemas.push(admin.last_modified.schema_version.clone());
+        }
+
+        for n in &r.names {
+            self.names.ids.push(id.clone());
+            self.names.values.push(n.value.clone());
+            self.names.name_types.push(get_name_type(&n.types));
+            self.names.is_ror_names.push(n.types.contains(&NameType::RorDisplay));
+            self.names.langs.push(n.lang.clone());
+        }
+
+        for t in &r.types {
+            self.types.ids.push(id.clone());
+            self.types.org_types.push(*t as i32);
+        }
+
+        for loc in &r.locations {
+            let g = &loc.geonames_details;
+            self.locations.ids.push(id.clone());
+            self.locations.geonames_ids.push(loc.geonames_id);
+            self.locations.names.push(g.name.clone());
+            self.locations.lats.push(g.lat);
+            self.locations.lngs.push(g.lng);
+            self.locations.cont_codes.push(g.continent_code.clone());
+            self.locations.cont_names.push(g.continent_name.clone());
+            self.locations.country_codes.push(g.country_code.clone());
+            self.locations.country_names.push(g.country_name.clone());
+            self.locations.csubdiv_codes.push(g.country_subdivision_code.clone());
+            self.locations.csubdiv_names.push(g.country_subdivision_name.clone());
+        }
+
+        for rel in &r.relationships {
+            self.relationships.ids.push(id.clone());
+            self.relationships.rel_types.push(rel.rel_type as i32);
+            self.relationships.related_ids.push(extract_id(&rel.id));
+            self.relationships.related_labels.push(rel.label.clone());
+        }
+
+        for ext in &r.external_ids {
+            for value in &ext.all {
+                self.external_ids.ids.push(id.clone());
+                self.external_ids.id_types.push(ext.id_type as i32);
+                self.external_ids.id_values.push(value.clone());
+                self.external_ids.is_preferreds.push(ext.preferred.as_ref() == Some(value));
+            }
+        }
+
+        for link in &r.links {
+            self.links.ids.push(id.clone());
+            self.links.link_types.push(link.link_type as i32);
+            self.links.values.push(link.value.clone());
+        }
+
+        for domain in &r.domains {
+            self.domains.ids.push(id.clone());
+            self.domains.values.push(domain.clone());
+        }
+    }
+
+
+    pub async fn store_data(&self, pool : &Pool<Postgres>) -> Result<(), AppError> {
+
+        let mut tx = pool.begin().await
+            .map_err(|e| AppError::SqlxError(e, "Starting batch import transaction".to_string()))?;
+
+        copy_rows_on_conn(&self.core, &mut tx).await?;
+        copy_rows_on_conn(&self.admin, &mut tx).await?;
+        copy_rows_on_conn(&self.names, &mut tx).await?;
+        copy_rows_on_conn(&self.types, &mut tx).await?;
+        copy_rows_on_conn(&self.locations, &mut tx).await?;
+        copy_rows_on_conn(&self.relationships, &mut tx).await?;
+        copy_rows_on_conn(&self.external_ids, &mut tx).await?;
+        copy_rows_on_conn(&self.links, &mut tx).await?;
+        copy_rows_on_conn(&self.domains, &mut tx).await?;
+
+        tx.commit().await
+            .map_err(|e| AppError::SqlxError(e, "Committing batch import transaction".to_string()))?;
+        Ok(())
+    }
+}
+
+
+impl CopyRows for CoreDataVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.core_data (id, ror_full_id, status, established) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.ror_full_ids[i])
+             .field(&self.statuses[i]).field(&self.estabs[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for AdminDataVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.admin_data (id, created, cr_schema, last_modified, lm_schema) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.created[i]).field(&self.cr_schemas[i])
+             .field(&self.last_modified[i]).field(&self.lm_schemas[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for NameVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.names (id, value, name_type, is_ror_name, lang) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.values[i]).field(&self.name_types[i])
+             .field(&self.is_ror_names[i]).field(&self.langs[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for TypeVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.type (id, org_type) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.org_types[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for LocationVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.locations (id, geonames_id, name, lat, lng, cont_code, cont_name, 
+         country_code, country_name, csubdiv_code, csubdiv_name) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.geonames_ids[i]).field(&self.names[i])
+             .field(&self.lats[i]).field(&self.lngs[i])
+             .field(&self.cont_codes[i]).field(&self.cont_names[i])
+             .field(&self.country_codes[i]).field(&self.country_names[i])
+             .field(&self.csubdiv_codes[i]).field(&self.csubdiv_names[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for RelationshipVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.relationships (id, rel_type, related_id, related_label) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.rel_types[i])
+             .field(&self.related_ids[i]).field(&self.related_labels[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for ExternalIdVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.external_ids (id, id_type, id_value, is_preferred) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.id_types[i])
+             .field(&self.id_values[i]).field(&self.is_preferreds[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for LinkVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.links (id, link_type, value) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.link_types[i]).field(&self.values[i]);
+            w.end_row();
+        }
+    }
+}
+
+impl CopyRows for DomainVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy ror.domains (id, value) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.ids.len() {
+            w.field(&self.ids[i]).field(&self.values[i]);
+            w.end_row();
+        }
+    }
+}
+
+
+// ROR ids are full urls, e.g. 'https://ror.org/04ttjf776' - only the final
+// 9 character section is used as the id within the database tables.
+
+pub fn extract_id(full_id: &str) -> String {
+    match full_id.rfind('/') {
+        Some(pos) => full_id[pos + 1..].to_string(),
+        None => full_id.to_string(),
+    }
+}
+
+// A name may have more than one type (e.g. 'ror_display' and 'label').
+// The ror_display type is recorded separately, as is_ror_name, and the
+// 'strongest' of the remaining types is used as the name type.
+
+fn get_name_type(types: &[NameType]) -> i32 {
+    if types.contains(&NameType::Acronym) {
+        NameType::Acronym as i32
+    }
+    else if types.contains(&NameType::Alias) {
+        NameType::Alias as i32
+    }
+    else {
+        NameType::Label as i32
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_id_extraction() {
+        assert_eq!(extract_id("https://ror.org/04ttjf776"), "04ttjf776");
+        assert_eq!(extract_id("04ttjf776"), "04ttjf776");
+    }
+
+    #[test]
+    fn check_name_types() {
+        assert_eq!(get_name_type(&[NameType::RorDisplay, NameType::Label]), 5);
+        assert_eq!(get_name_type(&[NameType::Alias]), 7);
+        assert_eq!(get_name_type(&[NameType::Acronym]), 10);
+        assert_eq!(get_name_type(&[NameType::RorDisplay]), 5);
+    }
+}
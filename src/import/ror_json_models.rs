@@ -0,0 +1,157 @@
+/***************************************************************************
+ * The structs and enums below mirror the ROR v2 json schema. Enumerated
+ * values (status, types, name types etc.) are deserialised directly into
+ * enums whose discriminants match the ids in the corresponding lup tables,
+ * so that the integer code can be obtained with a simple 'as i32'.
+ * The admin data and relationship labels are held as Options, because
+ * records converted from the v1 schema have no admin data and those read
+ * from the csv distribution no relationship labels, but they remain
+ * required fields in v2 json records.
+ ***************************************************************************/
+
+use serde::{Deserialize, Deserializer};
+
+
+#[derive(Debug, Deserialize)]
+pub struct RorRecord {
+    pub id: String,
+    #[serde(deserialize_with = "required_some")]
+    pub admin: Option<Admin>,
+    pub domains: Vec<String>,
+    pub established: Option<i32>,
+    pub external_ids: Vec<ExternalId>,
+    pub links: Vec<Link>,
+    pub locations: Vec<Location>,
+    pub names: Vec<Name>,
+    pub relationships: Vec<Relationship>,
+    pub status: OrgStatus,
+    pub types: Vec<OrgType>,
+}
+
+fn required_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Admin {
+    pub created: DateAndSchema,
+    pub last_modified: DateAndSchema,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DateAndSchema {
+    pub date: String,
+    pub schema_version: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExternalId {
+    #[serde(rename = "type")]
+    pub id_type: IdType,
+    pub all: Vec<String>,
+    pub preferred: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Link {
+    #[serde(rename = "type")]
+    pub link_type: LinkType,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Location {
+    pub geonames_id: i32,
+    pub geonames_details: GeonamesDetails,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeonamesDetails {
+    pub continent_code: Option<String>,
+    pub continent_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub country_subdivision_code: Option<String>,
+    pub country_subdivision_name: Option<String>,
+    pub lat: Option<f32>,
+    pub lng: Option<f32>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Name {
+    pub value: String,
+    pub types: Vec<NameType>,
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Relationship {
+    #[serde(rename = "type")]
+    pub rel_type: RelType,
+    #[serde(deserialize_with = "required_some")]
+    pub label: Option<String>,
+    pub id: String,
+}
+
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgStatus {
+    Active = 1,
+    Inactive = 2,
+    Withdrawn = 3,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgType {
+    Government = 100,
+    Education = 200,
+    Healthcare = 300,
+    Company = 400,
+    Nonprofit = 500,
+    Funder = 600,
+    Facility = 700,
+    Archive = 800,
+    Other = 900,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameType {
+    RorDisplay = 1,
+    Label = 5,
+    Alias = 7,
+    Acronym = 10,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IdType {
+    Isni = 11,
+    Wikidata = 12,
+    Grid = 13,
+    Fundref = 14,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkType {
+    Wikipedia = 21,
+    Website = 22,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelType {
+    Parent = 1,
+    Child = 2,
+    Related = 3,
+    Predecessor = 4,
+    Successor = 5,
+}
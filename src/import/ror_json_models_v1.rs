@@ -0,0 +1,299 @@
+/***************************************************************************
+ * Structs mirroring the ROR v1 json schema (schema version 1.0), used by
+ * the dumps published before the v2 schema was introduced. Rather than
+ * having a second set of data vectors each v1 record is converted into
+ * the v2 RorRecord structure, so that it is stored in the same ror tables
+ * with the same lup codes. The v1 name, labels, aliases and acronyms all
+ * become v2 names (ror_display / label, label, alias and acronym), the
+ * addresses become locations, and links and the wikipedia url become
+ * website and wikipedia links. v1 records have no admin data and no
+ * domains, and the OrgRef external ids, which have no equivalent in v2,
+ * are dropped.
+ ***************************************************************************/
+
+use serde::Deserialize;
+use super::ror_json_models::{RorRecord, ExternalId, GeonamesDetails, Link, Location, Name,
+                             Relationship, OrgStatus, OrgType, NameType, IdType, LinkType, RelType};
+
+
+#[derive(Debug, Deserialize)]
+pub struct RorRecordV1 {
+    pub id: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub acronyms: Vec<String>,
+    pub labels: Vec<LabelV1>,
+    pub established: Option<i32>,
+    pub types: Vec<OrgTypeV1>,
+    pub relationships: Vec<RelationshipV1>,
+    pub addresses: Vec<AddressV1>,
+    pub links: Vec<String>,
+    pub wikipedia_url: Option<String>,
+    pub status: OrgStatus,
+    pub country: CountryV1,
+    pub external_ids: ExternalIdsV1,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelV1 {
+    pub label: String,
+    pub iso639: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelationshipV1 {
+    #[serde(rename = "type")]
+    pub rel_type: RelTypeV1,
+    pub label: String,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddressV1 {
+    pub lat: Option<f32>,
+    pub lng: Option<f32>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub state_code: Option<String>,
+    pub geonames_city: Option<GeonamesCityV1>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeonamesCityV1 {
+    pub id: i32,
+    pub city: Option<String>,
+    pub geonames_admin1: Option<GeonamesAdminV1>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeonamesAdminV1 {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CountryV1 {
+    pub country_name: Option<String>,
+    pub country_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdsV1 {
+    #[serde(rename = "ISNI")]
+    pub isni: Option<ExternalIdV1>,
+    #[serde(rename = "Wikidata")]
+    pub wikidata: Option<ExternalIdV1>,
+    #[serde(rename = "GRID")]
+    pub grid: Option<ExternalIdV1>,
+    #[serde(rename = "FundRef")]
+    pub fundref: Option<ExternalIdV1>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExternalIdV1 {
+    pub preferred: Option<String>,
+    pub all: OneOrMany,
+}
+
+// In v1 the 'all' field of the GRID id is a single string rather than an array.
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum OrgTypeV1 {
+    Government,
+    Education,
+    Healthcare,
+    Company,
+    Nonprofit,
+    Facility,
+    Archive,
+    Other,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum RelTypeV1 {
+    Parent,
+    Child,
+    Related,
+    Predecessor,
+    Successor,
+}
+
+
+impl From<OrgTypeV1> for OrgType {
+    fn from(t: OrgTypeV1) -> Self {
+        match t {
+            OrgTypeV1::Government => OrgType::Government,
+            OrgTypeV1::Education => OrgType::Education,
+            OrgTypeV1::Healthcare => OrgType::Healthcare,
+            OrgTypeV1::Company => OrgType::Company,
+            OrgTypeV1::Nonprofit => OrgType::Nonprofit,
+            OrgTypeV1::Facility => OrgType::Facility,
+            OrgTypeV1::Archive => OrgType::Archive,
+            OrgTypeV1::Other => OrgType::Other,
+        }
+    }
+}
+
+impl From<RelTypeV1> for RelType {
+    fn from(t: RelTypeV1) -> Self {
+        match t {
+            RelTypeV1::Parent => RelType::Parent,
+            RelTypeV1::Child => RelType::Child,
+            RelTypeV1::Related => RelType::Related,
+            RelTypeV1::Predecessor => RelType::Predecessor,
+            RelTypeV1::Successor => RelType::Successor,
+        }
+    }
+}
+
+
+impl From<RorRecordV1> for RorRecord {
+    fn from(r: RorRecordV1) -> Self {
+
+        let mut names = vec![Name { value: r.name, types: vec![NameType::RorDisplay, NameType::Label], lang: None }];
+        for lab in r.labels {
+            names.push(Name { value: lab.label, types: vec![NameType::Label], lang: lab.iso639 });
+        }
+        for alias in r.aliases {
+            names.push(Name { value: alias, types: vec![NameType::Alias], lang: None });
+        }
+        for acro in r.acronyms {
+            names.push(Name { value: acro, types: vec![NameType::Acronym], lang: None });
+        }
+
+        let mut locations = Vec::new();
+        for addr in r.addresses {
+            if let Some(gc) = addr.geonames_city {
+
+                // The v2 subdivision code is the ISO 3166-2 code without the country,
+                // e.g. 'CA' from the v1 state code 'US-CA'. The geonames admin1 codes
+                // ('CO.34') are FIPS or numeric codes, and not the same, so the
+                // subdivision code is left null if there is no state code.
+
+                let admin1_name = gc.geonames_admin1.and_then(|a| a.name);
+                let csubdiv_code = addr.state_code.as_deref().and_then(|c| c.split_once('-'))
+                    .map(|(_, code)| code.to_string());
+
+                locations.push(Location {
+                    geonames_id: gc.id,
+                    geonames_details: GeonamesDetails {
+                        continent_code: None,
+                        continent_name: None,
+                        country_code: r.country.country_code.clone(),
+                        country_name: r.country.country_name.clone(),
+                        country_subdivision_code: csubdiv_code,
+                        country_subdivision_name: admin1_name.or(addr.state),
+                        lat: addr.lat,
+                        lng: addr.lng,
+                        name: gc.city.or(addr.city),
+                    },
+                });
+            }
+        }
+
+        let mut external_ids = Vec::new();
+        let v1_ids = [(r.external_ids.isni, IdType::Isni), (r.external_ids.wikidata, IdType::Wikidata),
+                      (r.external_ids.grid, IdType::Grid), (r.external_ids.fundref, IdType::Fundref)];
+        for (ext, id_type) in v1_ids {
+            if let Some(ext) = ext {
+                let all = match ext.all {
+                    OneOrMany::One(s) => vec![s],
+                    OneOrMany::Many(v) => v,
+                };
+                external_ids.push(ExternalId { id_type, all, preferred: ext.preferred });
+            }
+        }
+
+        let mut links: Vec<Link> = r.links.into_iter()
+            .map(|value| Link { link_type: LinkType::Website, value })
+            .collect();
+        if let Some(url) = r.wikipedia_url.filter(|u| !u.is_empty()) {
+            links.push(Link { link_type: LinkType::Wikipedia, value: url });
+        }
+
+        RorRecord {
+            id: r.id,
+            admin: None,
+            domains: Vec::new(),
+            established: r.established,
+            external_ids,
+            links,
+            locations,
+            names,
+            relationships: r.relationships.into_iter()
+                .map(|rel| Relationship { rel_type: rel.rel_type.into(), label: Some(rel.label), id: rel.id })
+                .collect(),
+            status: r.status,
+            types: r.types.into_iter().map(OrgType::from).collect(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_RECORD: &str = r#"{"id": "https://ror.org/02mhbdp94",
+        "name": "Universidad de los Andes", "email_address": null, "ip_addresses": [],
+        "established": 1948, "types": ["Education"],
+        "relationships": [{"label": "Fundación Santa Fe de Bogotá", "type": "Related", "id": "https://ror.org/03ezapm74"}],
+        "addresses": [{"lat": 4.60971, "lng": -74.08175, "state": null, "state_code": null, "city": "Bogotá",
+                       "geonames_city": {"id": 3688689, "city": "Bogotá",
+                                         "geonames_admin1": {"name": "Bogota D.C.", "id": 3688685, "ascii_name": "Bogota D.C.", "code": "CO.34"}},
+                       "postcode": null, "primary": false, "line": null, "country_geonames_id": 3686110}],
+        "links": ["https://uniandes.edu.co"], "aliases": [], "acronyms": ["UNIANDES"], "status": "active",
+        "wikipedia_url": "https://en.wikipedia.org/wiki/University_of_the_Andes_(Colombia)",
+        "labels": [{"label": "University of the Andes", "iso639": "en"}],
+        "country": {"country_name": "Colombia", "country_code": "CO"},
+        "external_ids": {"ISNI": {"preferred": null, "all": ["0000 0001 1033 6040"]},
+                         "OrgRef": {"preferred": null, "all": ["1045026"]},
+                         "GRID": {"preferred": "grid.7247.6", "all": "grid.7247.6"}}}"#;
+
+    #[test]
+    fn check_v1_record_converts_to_v2() {
+        let v1: RorRecordV1 = serde_json::from_str(V1_RECORD).unwrap();
+        let r = RorRecord::from(v1);
+
+        assert!(r.admin.is_none());
+        assert_eq!(r.status, OrgStatus::Active);
+        assert_eq!(r.types, vec![OrgType::Education]);
+        assert_eq!(r.relationships[0].rel_type, RelType::Related);
+
+        let name_types: Vec<(&str, Vec<NameType>, Option<&str>)> = r.names.iter()
+            .map(|n| (n.value.as_str(), n.types.clone(), n.lang.as_deref())).collect();
+        assert_eq!(name_types, vec![
+            ("Universidad de los Andes", vec![NameType::RorDisplay, NameType::Label], None),
+            ("University of the Andes", vec![NameType::Label], Some("en")),
+            ("UNIANDES", vec![NameType::Acronym], None),
+        ]);
+
+        let g = &r.locations[0].geonames_details;
+        assert_eq!(r.locations[0].geonames_id, 3688689);
+        assert_eq!(g.country_code.as_deref(), Some("CO"));
+        assert_eq!(g.country_subdivision_code, None);
+        assert_eq!(g.country_subdivision_name.as_deref(), Some("Bogota D.C."));
+        assert_eq!(g.name.as_deref(), Some("Bogotá"));
+
+        assert_eq!(r.external_ids.len(), 2);
+        assert_eq!(r.external_ids[1].id_type, IdType::Grid);
+        assert_eq!(r.external_ids[1].all, vec!["grid.7247.6".to_string()]);
+        assert_eq!(r.links.len(), 2);
+        assert_eq!(r.links[1].link_type, LinkType::Wikipedia);
+    }
+
+
+    #[test]
+    fn check_v1_state_code_gives_subdivision_code() {
+        let with_state = V1_RECORD.replace(r#""state_code": null"#, r#""state_code": "CO-DC""#);
+        let r = RorRecord::from(serde_json::from_str::<RorRecordV1>(&with_state).unwrap());
+        assert_eq!(r.locations[0].geonames_details.country_subdivision_code.as_deref(), Some("DC"));
+    }
+}
@@ -0,0 +1,316 @@
+/***************************************************************************
+ * The ROR dump is a single json array of well over 100,000 records, and is
+ * several hundred MB in size. Rather than reading the whole file into memory
+ * the array is consumed by a serde visitor, one record at a time, and the
+ * records are accumulated into batches (DataVecs) of a fixed size. Each full
+ * batch is sent down a bounded channel to the async code that stores it.
+ * Because the channel only holds a couple of batches the reader waits for
+ * the database whenever it gets ahead, and peak memory use is independent
+ * of the size of the source file.
+ * ROR releases are published as zip files that contain both json and csv
+ * versions of the data. If the source file is one of these the schema v2 
+ * json entry is read directly from the archive, without being extracted.
+ * Each element of the array is first read as a generic json value and then
+ * converted to a RorRecord, so that a record that does not match the schema
+ * can be written to the rejects file without halting the import.
+ * Both v1 and v2 schema files can be read. The schema version is taken from
+ * the file name if it has the 'schema_v2' suffix, and otherwise from the
+ * shape of the first record (v2 records have a 'names' array, v1 records a
+ * single 'name'). v1 records are converted to the v2 structure as read.
+ ***************************************************************************/
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use log::info;
+use zip::ZipArchive;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+use crate::AppError;
+use super::ror_json_models::RorRecord;
+use super::ror_json_models_v1::RorRecordV1;
+use super::ror_data_vectors::DataVecs;
+use super::ror_rejects::RejectsFile;
+
+
+pub fn read_records(file_path: &Path, batch_size: usize, tx: Sender<DataVecs>, 
+                    rejects: &mut RejectsFile) -> Result<usize, AppError> {
+
+    let file = File::open(file_path)
+                .map_err(|e| AppError::IoReadErrorWithPath(e, file_path.to_owned()))?;
+
+    if is_zip_file(file_path) {
+        let mut archive = ZipArchive::new(BufReader::new(file))
+            .map_err(|e| AppError::ImportError(format!("Unable to open zip file {}", file_path.display()), e.to_string()))?;
+        let entry_name = get_json_entry_name(archive.file_names())
+            .ok_or_else(|| AppError::ImportError(format!("No json file found in {}", file_path.display()),
+                                 "Zip file entries should include one ending in '.json'".to_string()))?;
+        info!("Reading {} from zip archive", entry_name);
+        let schema = schema_from_file_name(&entry_name);
+        let entry = archive.by_name(&entry_name)
+            .map_err(|e| AppError::ImportError(format!("Unable to read {} from zip file", entry_name), e.to_string()))?;
+        stream_records(BufReader::new(entry), schema, batch_size, tx, rejects)
+    }
+    else {
+        let schema = schema_from_file_name(&file_path.to_string_lossy());
+        stream_records(BufReader::new(file), schema, batch_size, tx, rejects)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SchemaVersion {
+    V1,
+    V2,
+}
+
+fn schema_from_file_name(file_name: &str) -> Option<SchemaVersion> {
+    if file_name.to_lowercase().contains("schema_v2") {
+        Some(SchemaVersion::V2)
+    }
+    else {
+        None
+    }
+}
+
+fn schema_from_record(value: &Value) -> Option<SchemaVersion> {
+    if value.get("names").is_some_and(|v| v.is_array()) {
+        Some(SchemaVersion::V2)
+    }
+    else if value.get("name").is_some_and(|v| v.is_string()) {
+        Some(SchemaVersion::V1)
+    }
+    else {
+        None
+    }
+}
+
+
+fn is_zip_file(file_path: &Path) -> bool {
+    match file_path.extension() {
+        Some(ext) => ext.eq_ignore_ascii_case("zip"),
+        None => false,
+    }
+}
+
+
+// The schema v2 json file is preferred, but older releases only contain
+// a single (v1) json file.
+
+fn get_json_entry_name<'a>(entry_names: impl Iterator<Item = &'a str>) -> Option<String> {
+    let json_names: Vec<&str> = entry_names
+        .filter(|n| !n.starts_with("__MACOSX") && n.to_lowercase().ends_with(".json"))
+        .collect();
+    json_names.iter()
+        .find(|n| n.to_lowercase().ends_with("schema_v2.json"))
+        .or(json_names.first())
+        .map(|n| n.to_string())
+}
+
+
+fn stream_records<R: Read>(rdr: R, schema: Option<SchemaVersion>, batch_size: usize, 
+                           tx: Sender<DataVecs>, rejects: &mut RejectsFile) -> Result<usize, AppError> {
+
+    let mut deserializer = serde_json::Deserializer::from_reader(rdr);
+    let batcher = RecordBatcher { schema, batch_size, tx, rejects };
+    let record_count = deserializer.deserialize_seq(batcher)?;
+    deserializer.end()?;
+    Ok(record_count)
+}
+
+
+struct RecordBatcher<'a> {
+    schema: Option<SchemaVersion>,
+    batch_size: usize,
+    tx: Sender<DataVecs>,
+    rejects: &'a mut RejectsFile,
+}
+
+impl<'de> Visitor<'de> for RecordBatcher<'_> {
+
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a json array of ROR records")
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut record_count = 0;
+        let mut dv = DataVecs::default();
+
+        while let Some(value) = seq.next_element::<Value>()? {
+
+            // If not known from the file name the schema version is fixed by the
+            // first record. Any later record with the other shape is rejected.
+
+            let schema = match self.schema {
+                Some(schema) => schema,
+                None => {
+                    let schema = schema_from_record(&value).unwrap_or(SchemaVersion::V2);
+                    info!("Source data identified as ROR schema {:?} from the first record", schema);
+                    self.schema = Some(schema);
+                    schema
+                }
+            };
+
+            let res = match schema {
+                SchemaVersion::V2 => serde_path_to_error::deserialize::<_, RorRecord>(&value),
+                SchemaVersion::V1 => serde_path_to_error::deserialize::<_, RorRecordV1>(&value).map(RorRecord::from),
+            };
+            let r = match res {
+                Ok(r) => r,
+                Err(e) => {
+                    let id = value.get("id").and_then(|v| v.as_str()).unwrap_or("(no id)");
+                    self.rejects.add(id, &e.path().to_string(), &e.inner().to_string())
+                        .map_err(|e| de::Error::custom(format!("unable to write to rejects file: {}", e)))?;
+                    continue;
+                }
+            };
+            dv.add_record(&r);
+            record_count += 1;
+            if dv.record_count() == self.batch_size {
+                let full_batch = std::mem::take(&mut dv);
+                self.tx.blocking_send(full_batch)
+                    .map_err(|_| de::Error::custom("batch receiver closed before end of file"))?;
+            }
+        }
+
+        if dv.record_count() > 0 {
+            self.tx.blocking_send(dv)
+                .map_err(|_| de::Error::custom("batch receiver closed before end of file"))?;
+        }
+
+        Ok(record_count)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn test_rejects_file(name: &str) -> RejectsFile {
+        RejectsFile::new(&std::env::temp_dir(), &format!("mk_org {} test.json", name))
+    }
+
+    fn test_record(n: usize) -> String {
+        format!(r#"{{"id": "https://ror.org/0000000{n:02}",
+            "admin": {{"created": {{"date": "2020-01-01", "schema_version": "1.0"}},
+                     "last_modified": {{"date": "2025-01-01", "schema_version": "2.1"}}}},
+            "domains": [], "established": null, "external_ids": [], "links": [],
+            "locations": [], "relationships": [], "status": "active", "types": ["education"],
+            "names": [{{"value": "Org {n}", "types": ["ror_display", "label"], "lang": null}}]}}"#)
+    }
+
+    #[test]
+    fn check_records_are_batched() {
+        let json = format!("[{}]", (0..7).map(test_record).collect::<Vec<String>>().join(","));
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = test_rejects_file("batching");
+        let res = stream_records(json.as_bytes(), None, 3, tx, &mut rejects).unwrap();
+        assert_eq!(res, 7);
+        assert_eq!(rejects.count(), 0);
+
+        let mut batch_sizes = Vec::new();
+        while let Ok(dv) = rx.try_recv() {
+            batch_sizes.push(dv.record_count());
+        }
+        assert_eq!(batch_sizes, vec![3, 3, 1]);
+    }
+
+    #[test]
+    fn check_empty_array_gives_no_batches() {
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = test_rejects_file("empty");
+        let res = stream_records("[]".as_bytes(), None, 3, tx, &mut rejects).unwrap();
+        assert_eq!(res, 0);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn check_schema_v2_json_entry_is_selected() {
+        let entries = vec!["v1.59-2025-01-23-ror-data.csv", "v1.59-2025-01-23-ror-data.json",
+                           "v1.59-2025-01-23-ror-data_schema_v2.csv", "v1.59-2025-01-23-ror-data_schema_v2.json"];
+        assert_eq!(get_json_entry_name(entries.into_iter()), Some("v1.59-2025-01-23-ror-data_schema_v2.json".to_string()));
+
+        let entries = vec!["v1.20-2023-02-28-ror-data.csv", "v1.20-2023-02-28-ror-data.json"];
+        assert_eq!(get_json_entry_name(entries.into_iter()), Some("v1.20-2023-02-28-ror-data.json".to_string()));
+
+        let entries = vec!["v1.59-2025-01-23-ror-data.csv", "v1.59-2025-01-23-ror-data_schema_v2.csv"];
+        assert_eq!(get_json_entry_name(entries.into_iter()), None);
+    }
+
+    #[test]
+    fn check_schema_version_detection() {
+        assert_eq!(schema_from_file_name("v1.59-2025-01-23-ror-data_schema_v2.json"), Some(SchemaVersion::V2));
+        assert_eq!(schema_from_file_name("v1.59-2025-01-23-ror-data.json"), None);
+
+        let v2: Value = serde_json::from_str(&test_record(1)).unwrap();
+        assert_eq!(schema_from_record(&v2), Some(SchemaVersion::V2));
+        let v1: Value = serde_json::from_str(r#"{"id": "https://ror.org/000000001", "name": "Org 1"}"#).unwrap();
+        assert_eq!(schema_from_record(&v1), Some(SchemaVersion::V1));
+    }
+
+    #[test]
+    fn check_v1_records_are_read() {
+        let v1_record = |n: usize| format!(r#"{{"id": "https://ror.org/0000000{n:02}", "name": "Org {n}",
+            "aliases": [], "acronyms": ["O{n}"], "labels": [], "established": null, "types": ["Facility"],
+            "relationships": [], "addresses": [], "links": [], "wikipedia_url": null, "status": "active",
+            "country": {{"country_name": "France", "country_code": "FR"}}, "external_ids": {{}}}}"#);
+        let json = format!("[{}, {}, {}]", v1_record(1), v1_record(2), test_record(3));
+
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = test_rejects_file("v1");
+        let res = stream_records(json.as_bytes(), None, 10, tx, &mut rejects).unwrap();
+        rejects.finish().unwrap();
+        assert_eq!(res, 2);
+        assert_eq!(rejects.count(), 1);   // the v2 record
+        let dv = rx.try_recv().unwrap();
+        assert_eq!(dv.record_count(), 2);
+        assert_eq!(dv.names.name_types, vec![5, 10, 5, 10]);
+        std::fs::remove_file(rejects.path()).unwrap();
+    }
+
+    #[test]
+    fn check_zip_file_recognised() {
+        assert!(is_zip_file(Path::new("v1.59-2025-01-23-ror-data.zip")));
+        assert!(is_zip_file(Path::new("v1.59-2025-01-23-ror-data.ZIP")));
+        assert!(!is_zip_file(Path::new("v1.59-2025-01-23-ror-data_schema_v2.json")));
+    }
+
+    #[test]
+    fn check_non_array_is_rejected() {
+        let (tx, _rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = test_rejects_file("non_array");
+        let res = stream_records(test_record(1).as_bytes(), None, 3, tx, &mut rejects);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn check_invalid_records_are_rejected() {
+        let bad_status = test_record(2).replace(r#""status": "active""#, r#""status": "defunct""#);
+        let bad_name = test_record(3).replace(r#""lang": null"#, r#""lang": 12"#);
+        let json = format!("[{}, {}, {}, {}]", test_record(1), bad_status, bad_name, test_record(4));
+
+        let (tx, mut rx) = mpsc::channel::<DataVecs>(10);
+        let mut rejects = test_rejects_file("invalid");
+        let res = stream_records(json.as_bytes(), Some(SchemaVersion::V2), 10, tx, &mut rejects).unwrap();
+        rejects.finish().unwrap();
+        assert_eq!(res, 2);
+        assert_eq!(rejects.count(), 2);
+        assert_eq!(rx.try_recv().unwrap().record_count(), 2);
+
+        let contents = std::fs::read_to_string(rejects.path()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], "id,json_path,error");
+        assert!(lines[1].starts_with("https://ror.org/000000002,status,\"unknown variant `defunct`"));
+        assert!(lines[2].starts_with("https://ror.org/000000003,names[0].lang,\"invalid type"));
+        std::fs::remove_file(rejects.path()).unwrap();
+    }
+}
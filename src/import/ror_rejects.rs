@@ -0,0 +1,67 @@
+/***************************************************************************
+ * Records that cannot be deserialised into the ROR schema structs are not
+ * allowed to stop the import. Instead the record's id, the json path of the
+ * offending element and the error message are written to a csv 'rejects'
+ * file in the outputs folder, and the import continues. The file is only
+ * created if at least one record is rejected.
+ ***************************************************************************/
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use csv::Writer;
+use crate::AppError;
+
+
+pub struct RejectsFile {
+    path: PathBuf,
+    writer: Option<Writer<File>>,
+    count: usize,
+}
+
+impl RejectsFile {
+
+    pub fn new(output_folder: &Path, source_file_name: &str) -> Self {
+        let source_stem = match Path::new(source_file_name).file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => source_file_name.to_string(),
+        };
+        RejectsFile {
+            path: output_folder.join(format!("{} rejects.csv", source_stem)),
+            writer: None,
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, id: &str, json_path: &str, error: &str) -> Result<(), AppError> {
+
+        if self.writer.is_none() {
+            let mut wtr = Writer::from_path(&self.path)
+                .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), self.path.clone()))?;
+            wtr.write_record(["id", "json_path", "error"])
+                .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), self.path.clone()))?;
+            self.writer = Some(wtr);
+        }
+
+        if let Some(wtr) = self.writer.as_mut() {
+            wtr.write_record([id, json_path, error])
+                .map_err(|e| AppError::IoWriteErrorWithPath(e.into(), self.path.clone()))?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        if let Some(wtr) = self.writer.as_mut() {
+            wtr.flush().map_err(|e| AppError::IoWriteErrorWithPath(e, self.path.clone()))?;
+        }
+        Ok(())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
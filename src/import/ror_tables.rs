@@ -0,0 +1,158 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+pub async fn create_tables(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // The version details table is not recreated, as it holds the history
+    // of all imports, the latest being the data currently in the ror tables.
+
+    let sql = r#"SET client_min_messages TO WARNING;
+    create schema if not exists ror;
+
+    create table if not exists ror.version_details (
+        version           varchar     not null
+      , data_date         date        not null
+      , source_file       varchar     not null
+      , imported          timestamp   not null default now()
+    );
+
+    drop table if exists ror.core_data;
+    create table ror.core_data (
+        id                varchar     not null primary key
+      , ror_full_id       varchar     not null
+      , status            int         not null
+      , established       int         null
+    );
+
+    drop table if exists ror.admin_data;
+    create table ror.admin_data (
+        id                varchar     not null primary key
+      , created           date        not null
+      , cr_schema         varchar     not null
+      , last_modified     date        not null
+      , lm_schema         varchar     not null
+    );
+
+    drop table if exists ror.names;
+    create table ror.names (
+        id                varchar     not null
+      , value             varchar     not null
+      , name_type         int         not null
+      , is_ror_name       bool        not null
+      , lang              varchar     null
+    );
+    create index names_idx on ror.names(id);
+
+    drop table if exists ror.type;
+    create table ror.type (
+        id                varchar     not null
+      , org_type          int         not null
+    );
+    create index type_idx on ror.type(id);
+
+    drop table if exists ror.locations;
+    create table ror.locations (
+        id                varchar     not null
+      , geonames_id       int         not null
+      , name              varchar     null
+      , lat               real        null
+      , lng               real        null
+      , cont_code         varchar     null
+      , cont_name         varchar     null
+      , country_code      varchar     null
+      , country_name      varchar     null
+      , csubdiv_code      varchar     null
+      , csubdiv_name      varchar     null
+    );
+    create index locations_idx on ror.locations(id);
+
+    drop table if exists ror.relationships;
+    create table ror.relationships (
+        id                varchar     not null
+      , rel_type          int         not null
+      , related_id        varchar     not null
+      , related_label     varchar     null
+    );
+    create index relationships_idx on ror.relationships(id);
+
+    drop table if exists ror.external_ids;
+    create table ror.external_ids (
+        id                varchar     not null
+      , id_type           int         not null
+      , id_value          varchar     not null
+      , is_preferred      bool        not null
+    );
+    create index external_ids_idx on ror.external_ids(id);
+
+    drop table if exists ror.links;
+    create table ror.links (
+        id                varchar     not null
+      , link_type         int         not null
+      , value             varchar     not null
+    );
+    create index links_idx on ror.links(id);
+
+    drop table if exists ror.domains;
+    create table ror.domains (
+        id                varchar     not null
+      , value             varchar     not null
+    );
+    create index domains_idx on ror.domains(id);
+
+    SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(pool).await
+         .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    Ok(())
+}
+
+
+// Used when the data has been imported from the csv distribution, which
+// does not include the names of related organisations.
+
+pub async fn add_related_labels(pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let sql = r#"update ror.relationships r
+    set related_label = n.value
+    from ror.names n
+    where r.related_id = n.id
+    and n.is_ror_name = true
+    and r.related_label is null;"#;
+    let res = sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(res.rows_affected())
+}
+
+
+pub async fn store_version_details(data_version: &str, data_date: &str, source_file_name: &str,
+                                   pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = r#"insert into ror.version_details (version, data_date, source_file)
+    values ($1, $2::date, $3);"#;
+    sqlx::query(sql).bind(data_version).bind(data_date).bind(source_file_name)
+        .execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(())
+}
+
+
+pub async fn fetch_latest_version(pool: &Pool<Postgres>) -> Result<Option<(String, String)>, AppError> {
+
+    // The table will not exist if no import has yet taken place.
+
+    let sql = "select to_regclass('ror.version_details') is not null";
+    let table_exists: bool = sqlx::query_scalar(sql).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !table_exists {
+        return Ok(None);
+    }
+
+    let sql = r#"select version, to_char(data_date, 'YYYY-MM-DD')
+    from ror.version_details
+    order by imported desc
+    limit 1;"#;
+    sqlx::query_as(sql).fetch_optional(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
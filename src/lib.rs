@@ -3,13 +3,19 @@ pub mod err;
 mod import;
 mod process;
 mod extra;
+mod summarise;
+mod export;
 
 
 use setup::cli_reader;
+use setup::cli_reader::Flags;
+use setup::InitParams;
 use err::AppError;
 use std::ffi::OsString;
 use std::fs;
 use std::path::PathBuf;
+use log::info;
+use sqlx::PgPool;
 
 pub async fn run(args: Vec<OsString>) -> Result<(), AppError> {
     
@@ -43,18 +49,58 @@ pub async fn run(args: Vec<OsString>) -> Result<(), AppError> {
     let config_string: String = fs::read_to_string(&config_file)
                     .map_err(|e| AppError::IoReadErrorWithPath(e, config_file))?;
     
-    let params = setup::get_params(cli_pars, &config_string)?;
+    let mut params = setup::get_params(cli_pars, &config_string)?;
 
     setup::establish_log(&params, &config_string)?;
-    let pool = setup::get_db_pool().await?;
-    let test_run = flags.test_run;
 
-    // The first two routines below normally run only as an initial 
+    // A test run works in its own database, which is given the lookup and
+    // summary tables it needs, and which is dropped when the run ends, whether 
+    // or not the run has succeeded. Production tables are never touched.
+
+    if flags.test_run {
+        let pool = setup::get_test_db_pool(&params.data_folder).await?;
+        let res = match setup::create_lup_tables(&pool).await {
+            Ok(()) => match summarise::create_smm_tables(&pool).await {
+                Ok(()) => run_steps(flags, &mut params, &pool).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+
+        // An error in the run itself takes precedence - any failure to drop
+        // the test database is then reported but not returned.
+
+        let drop_res = setup::drop_test_db(pool, &params.data_folder).await;
+        match res {
+            Ok(()) => drop_res,
+            Err(e) => {
+                if let Err(drop_err) = drop_res {
+                    err::report_error(drop_err);
+                }
+                Err(e)
+            },
+        }
+    }
+    else {
+        let pool = setup::get_db_pool().await?;
+        run_steps(flags, &mut params, &pool).await
+    }
+}
+
+
+async fn run_steps(flags: Flags, params: &mut InitParams, pool: &PgPool) -> Result<(), AppError> {
+
+    // The first three routines below normally run only as an initial 
     // 'setup' of the program's config file and DB, but can be repeated later if required.
 
     if flags.create_lookups
     {  
-        setup::create_lup_tables(&pool).await?;
+        setup::create_lup_tables(pool).await?;
+    }
+
+    if flags.create_summary
+    {
+        summarise::create_smm_tables(pool).await?;
     }
     
     // The routines below run as part of the 'normal' functioning of the program.
@@ -62,31 +108,70 @@ pub async fn run(args: Vec<OsString>) -> Result<(), AppError> {
 
     if flags.import_ror    // import ror from json file and store in ror schema tables
     {
-        
+        import::create_ror_tables(pool).await?;
+        import::import_data(&params.data_folder, &params.output_folder, &params.source_file_name, pool).await?;
+        import::record_version(&params.data_version, &params.data_date, &params.source_file_name, pool).await?;
+    }
+
+    // The processing stages always work on the most recently imported data, which
+    // provides the version and date if they have not been specified. Exports
+    // default to that version but can be of any version in the summary tables.
+
+    if flags.process_data || flags.additional_processing || flags.evaluate
+    {
+        let stored = import::get_current_version(pool).await?;
+        (params.data_version, params.data_date) = setup::check_data_version(&params.data_version, stored)?;
+        info!("Processing data version {} ({})", params.data_version, params.data_date);
+    }
+    else if (flags.export_text || flags.export_csv || flags.export_uncoded) && params.data_version.is_empty()
+    {
+        let stored = import::get_current_version(pool).await?;
+        (params.data_version, params.data_date) = setup::check_data_version("", stored)?;
     }
 
 
     if flags.process_data  // transfer data to src tables, and summarise in smm tables
     {
-        process::create_src_tables(&pool).await?;
-        process::process_data(&params.data_version, &pool).await?;
+        process::create_src_tables(pool).await?;
+        process::process_data(&params.data_version, pool).await?;
+        summarise::summarise_data(&params.data_version, &params.data_date, "src", pool).await?;
     }
 
 
     if flags.additional_processing  // add language codes to as many names as possible
     {
-        extra::load_data(&pool).await?;
-        extra::prep_names(&pool).await?;
-        extra::apply_name_codes(&pool).await?;
-        extra::apply_acro_codes(&pool).await?;
+        if flags.dry_run {  // report the codes that would be applied, but store nothing
+            extra::dry_run_code_names(&params.data_folder, pool).await?;
+        }
+        else {
+            extra::code_names(&params.data_folder, pool).await?;
+            summarise::summarise_data(&params.data_version, &params.data_date, "ext", pool).await?;
+        }
+    }
 
-        // extra::complete_rels(&pool).await?;
-        // extra::rationalise_companies(&pool).await?;
+    if flags.evaluate  // compare the language codes from the heuristics with those from ror
+    {
+        extra::evaluate_coding(&params.data_folder, &params.output_folder, &params.data_version, pool).await?;
+    }
 
+    if flags.export_uncoded  // write the names still without a lang code to a csv file, for manual coding
+    {
+        extra::export_uncoded_names(&params.data_version, &params.output_folder, pool).await?;
     }
 
-    if test_run {  // Clear any test data from the smm tables.
-        //  summarise::smm_helper::delete_any_existing_data(&"v99".to_string(), &pool).await?; 
+    if flags.export_text  // write a summary of the current or specified version to a text file
+    {
+        export::export_text(&params.data_version, &params.output_folder, pool).await?;
+    }
+
+    if flags.export_csv  // write the summary of the current or specified version to csv files
+    {
+        export::export_csv(&params.data_version, &params.output_folder, pool).await?;
+    }
+
+    if flags.export_full_csv  // write the summaries of all versions to csv files
+    {
+        export::export_full_csv(&params.output_folder, pool).await?;
     }
 
     Ok(())  
@@ -1,29 +1,50 @@
-// use log::{info, error};
+/***************************************************************************
+ * The process module transfers the data from the ror schema, which holds
+ * the data much as it is in the ROR source file, to the src schema, which
+ * is the starting point for the additional processing in the ext schema.
+ * Each org is flattened into core data that includes its ror name and its
+ * (first) location, and the ror name is added to the other org tables.
+ * Language codes are carried over from the source data, and each name is
+ * given a script code (and a secondary script code if it mixes scripts),
+ * derived from the unicode ranges in lup.lang_scripts.
+ ***************************************************************************/
+
+mod src_tables;
+mod src_data;
+mod script_coder;
+
+use log::{info, error};
 use sqlx::{Pool, Postgres};
 use crate::AppError;
 
 
-pub async fn create_src_tables(_pool : &Pool<Postgres>) -> Result<(), AppError>
+pub async fn create_src_tables(pool : &Pool<Postgres>) -> Result<(), AppError>
 {
-   
+    match src_tables::create_tables(pool).await {
+        Ok(()) => info!("Tables created for src schema"),
+        Err(e) => {
+            error!("An error occured while creating the src schema tables: {}", e);
+            return Err(e)
+            },
+    };
     Ok(())
-
 }
 
-pub async fn process_data(_data_version: &String, _pool : &Pool<Postgres>) -> Result<(), AppError>
+pub async fn process_data(_data_version: &String, pool : &Pool<Postgres>) -> Result<(), AppError>
 {
 
     // Import the data from ror schema to src schema.
 
-    // if data version = "" obtain it from the ror tables
-
+    src_data::transfer_core_data(pool).await?;
+    src_data::transfer_names(pool).await?;
+    src_data::transfer_relationships(pool).await?;
+    src_data::transfer_types(pool).await?;
+    src_data::transfer_locations(pool).await?;
 
-    // Update lang codes from scripts where possible, record lang code source type
+    // Add script codes to the names, so that lang codes can later be derived
+    // from the script where possible.
 
-    //src_script_coder::update_lang_code_source("ror", pool).await?;
-    //src_script_coder::add_langs_for_nonlatin_codes(pool).await?;
-    //src_script_coder::update_lang_code_source("script_auto", pool).await?;
+    script_coder::add_script_codes(pool).await?;
 
     Ok(())
 }
-
@@ -0,0 +1,215 @@
+/***************************************************************************
+ * Each name is given a script code, derived from the unicode ranges held
+ * in lup.lang_scripts. The letters in a name (digits, punctuation and
+ * spaces are ignored) are allocated to the script whose range contains
+ * them, and the script with the most letters becomes the name's script
+ * code. If a name mixes scripts the script with the next highest number of
+ * letters is recorded as the secondary script. Names without any letters
+ * in a listed range are treated as Latin. Supplementary ranges, such as
+ * 'Latn2' (Latin Extended), are reported using their base script code.
+ * Scripts are computed once for each distinct name value, and the results
+ * returned to the database using COPY, via a work table that is then
+ * joined to src.names.
+ ***************************************************************************/
+
+use std::collections::HashMap;
+use sqlx::{Pool, Postgres};
+use log::info;
+use crate::AppError;
+use crate::import::copy_writer::{copy_rows, CopyRows, CopyWriter};
+
+
+struct ScriptRange {
+    code: String,
+    start: u32,
+    end: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct NameScripts {
+    pub script_code: String,
+    pub secondary_script: Option<String>,
+}
+
+
+pub struct ScriptCoder {
+    ranges: Vec<ScriptRange>,
+}
+
+impl ScriptCoder {
+
+    pub async fn load(pool: &Pool<Postgres>) -> Result<Self, AppError> {
+
+        let sql = r#"select code, ascii_start, ascii_end
+                from lup.lang_scripts
+                where ascii_end > 0
+                order by ascii_start;"#;
+        let rows: Vec<(String, i32, i32)> = sqlx::query_as(sql).fetch_all(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+        Ok(ScriptCoder::from_ranges(rows))
+    }
+
+    fn from_ranges(rows: Vec<(String, i32, i32)>) -> Self {
+        let ranges = rows.into_iter()
+            .map(|(code, start, end)| ScriptRange {
+                code: code.trim_end_matches(|c: char| c.is_ascii_digit()).to_string(),
+                start: start as u32,
+                end: end as u32,
+            })
+            .collect();
+        ScriptCoder { ranges }
+    }
+
+    fn script_of(&self, c: char) -> Option<&str> {
+        let cp = c as u32;
+        self.ranges.iter()
+            .find(|r| r.start <= cp && cp <= r.end)
+            .map(|r| r.code.as_str())
+    }
+
+    pub fn classify(&self, name: &str) -> NameScripts {
+
+        // Counts are kept in order of first appearance, so that ties
+        // are resolved in favour of the script that appears first.
+
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for c in name.chars().filter(|c| c.is_alphabetic()) {
+            if let Some(code) = self.script_of(c) {
+                match counts.iter_mut().find(|(k, _)| *k == code) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((code, 1)),
+                }
+            }
+        }
+        counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));   // stable, so first appearance retained on ties
+
+        NameScripts {
+            script_code: counts.first().map(|(k, _)| k.to_string()).unwrap_or("Latn".to_string()),
+            secondary_script: counts.get(1).map(|(k, _)| k.to_string()),
+        }
+    }
+}
+
+
+#[derive(Default)]
+struct ScriptVecs {
+    values: Vec<String>,
+    script_codes: Vec<String>,
+    secondary_scripts: Vec<Option<String>>,
+}
+
+impl CopyRows for ScriptVecs {
+    fn copy_statement(&self) -> &'static str {
+        "copy src.name_scripts (value, script_code, secondary_script) from stdin"
+    }
+    fn write_rows(&self, w: &mut CopyWriter) {
+        for i in 0..self.values.len() {
+            w.field(&self.values[i]).field(&self.script_codes[i]).field(&self.secondary_scripts[i]);
+            w.end_row();
+        }
+    }
+}
+
+
+pub async fn add_script_codes(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let coder = ScriptCoder::load(pool).await?;
+
+    let sql = "select distinct value from src.names;";
+    let values: Vec<String> = sqlx::query_scalar(sql).fetch_all(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut sv = ScriptVecs::default();
+    let mut script_counts: HashMap<String, usize> = HashMap::new();
+    for value in values {
+        let ns = coder.classify(&value);
+        *script_counts.entry(ns.script_code.clone()).or_insert(0) += 1;
+        sv.values.push(value);
+        sv.script_codes.push(ns.script_code);
+        sv.secondary_scripts.push(ns.secondary_script);
+    }
+
+    let sql = r#"SET client_min_messages TO WARNING;
+            drop table if exists src.name_scripts;
+            create table src.name_scripts (
+                value             varchar     not null
+              , script_code       varchar     not null
+              , secondary_script  varchar     null
+            );
+            SET client_min_messages TO NOTICE;"#;
+    sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    copy_rows(&sv, pool).await?;
+
+    let sql = r#"create index name_scripts_idx on src.name_scripts(value);
+            update src.names n
+            set script_code = s.script_code,
+            secondary_script = s.secondary_script
+            from src.name_scripts s
+            where n.value = s.value;"#;
+    let res = sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    info!("{} names given script codes", res.rows_affected());
+
+    let sql = "drop table src.name_scripts;";
+    sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    let mut script_counts: Vec<(String, usize)> = script_counts.into_iter().collect();
+    script_counts.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    for (code, n) in script_counts {
+        info!("{} distinct names with script {}", n, code);
+    }
+    let mixed = sv.secondary_scripts.iter().filter(|s| s.is_some()).count();
+    info!("{} distinct names with a secondary script", mixed);
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_coder() -> ScriptCoder {
+        ScriptCoder::from_ranges(vec![
+            ("Latn".to_string(), 0, 767), ("Grek".to_string(), 880, 1023),
+            ("Cyrl".to_string(), 1024, 1279), ("Hira".to_string(), 12352, 12447),
+            ("Hani".to_string(), 19968, 40959), ("Latn2".to_string(), 7680, 7935),
+        ])
+    }
+
+    #[test]
+    fn check_single_script_names() {
+        let coder = test_coder();
+        assert_eq!(coder.classify("Université de Lyon"),
+                   NameScripts { script_code: "Latn".to_string(), secondary_script: None });
+        assert_eq!(coder.classify("Московский университет"),
+                   NameScripts { script_code: "Cyrl".to_string(), secondary_script: None });
+        assert_eq!(coder.classify("北京大学"),
+                   NameScripts { script_code: "Hani".to_string(), secondary_script: None });
+    }
+
+    #[test]
+    fn check_mixed_script_names() {
+        let coder = test_coder();
+        assert_eq!(coder.classify("ΑΧΕΠΑ Society"),
+                   NameScripts { script_code: "Latn".to_string(), secondary_script: Some("Grek".to_string()) });
+        assert_eq!(coder.classify("東京のUniv"),
+                   NameScripts { script_code: "Latn".to_string(), secondary_script: Some("Hani".to_string()) });
+        assert_eq!(coder.classify("ОАО Gazprom"),
+                   NameScripts { script_code: "Latn".to_string(), secondary_script: Some("Cyrl".to_string()) });
+    }
+
+    #[test]
+    fn check_digits_and_extended_ranges() {
+        let coder = test_coder();
+        assert_eq!(coder.classify("Trường Đại học Cần Thơ").script_code, "Latn");
+        assert_eq!(coder.classify("Trường Đại học Cần Thơ").secondary_script, None);
+        assert_eq!(coder.classify("1917 - 2017"),
+                   NameScripts { script_code: "Latn".to_string(), secondary_script: None });
+        assert_eq!(coder.classify("МГУ 1755"),
+                   NameScripts { script_code: "Cyrl".to_string(), secondary_script: None });
+    }
+}
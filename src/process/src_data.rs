@@ -0,0 +1,120 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+use log::{info, warn};
+
+
+async fn execute_sql(sql: &str, pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    let res = sqlx::raw_sql(sql).execute(pool)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    Ok(res.rows_affected())
+}
+
+
+pub async fn transfer_core_data(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // The ror name is the name with the ror_display type. Orgs without one
+    // (possible in v1 or csv imports) are given their first label, or failing
+    // that any other name, rather than being dropped. Where an org has more 
+    // than one location the location fields are taken from the one with the
+    // lowest geonames id (the order of locations in the record is not kept).
+
+    let sql = r#"select count(*) from ror.core_data c
+            where not exists (select 1 from ror.names n
+                              where n.id = c.id and n.is_ror_name = true);"#;
+    let num_without: i64 = sqlx::query_scalar(sql).fetch_one(pool)
+        .await.map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if num_without > 0 {
+        warn!("{} orgs have no ror_display name, and are given another of their names as the ror name", num_without);
+    }
+
+    let sql = r#"insert into src.core_data (id, ror_full_id, ror_name, 
+            status, established, location, csubdiv_code, country_code)
+            select c.id, c.ror_full_id, coalesce(n.value, ''), 
+            c.status, c.established, loc.name, loc.csubdiv_code, loc.country_code
+            from ror.core_data c
+            left join 
+                (select distinct on (id) id, value
+                 from ror.names
+                 order by id, is_ror_name desc, name_type, value) n
+            on c.id = n.id
+            left join 
+                (select distinct on (id) id, name, csubdiv_code, country_code
+                 from ror.locations
+                 order by id, geonames_id) loc
+            on c.id = loc.id;"#;
+
+    let res = execute_sql(sql, pool).await?;
+    info!("{} core data records transferred to src schema", res);
+    Ok(())
+}
+
+
+pub async fn transfer_names(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Any language codes at this stage are those provided by ROR.
+
+    let sql = r#"insert into src.names (id, value, name_type, 
+            is_ror_name, lang_code, lang_source)
+            select id, value, name_type, 
+            is_ror_name, lang, case when lang is not null then 'ror' end
+            from ror.names;"#;
+
+    let res = execute_sql(sql, pool).await?;
+    info!("{} names transferred to src schema", res);
+    Ok(())
+}
+
+
+pub async fn transfer_relationships(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Related names taken from the related org's record, falling back
+    // on the label held in the relationship itself.
+
+    let sql = r#"insert into src.relationships (id, ror_name, rel_type, 
+            related_id, related_name)
+            select r.id, c.ror_name, r.rel_type, 
+            r.related_id, coalesce(rc.ror_name, r.related_label, '')
+            from ror.relationships r
+            inner join src.core_data c
+            on r.id = c.id
+            left join src.core_data rc
+            on r.related_id = rc.id;"#;
+
+    let res = execute_sql(sql, pool).await?;
+    info!("{} relationship records transferred to src schema", res);
+    Ok(())
+}
+
+
+pub async fn transfer_types(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = r#"insert into src.type (id, ror_name, org_type)
+            select t.id, c.ror_name, t.org_type
+            from ror.type t
+            inner join src.core_data c
+            on t.id = c.id;"#;
+
+    let res = execute_sql(sql, pool).await?;
+    info!("{} type records transferred to src schema", res);
+    Ok(())
+}
+
+
+pub async fn transfer_locations(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = r#"insert into src.locations (id, ror_name, geonames_id, 
+            location, lat, lng, cont_code, cont_name, 
+            country_code, country_name, csubdiv_code, csubdiv_name)
+            select g.id, c.ror_name, g.geonames_id, 
+            g.name, g.lat, g.lng, g.cont_code, g.cont_name, 
+            g.country_code, g.country_name, g.csubdiv_code, g.csubdiv_name
+            from ror.locations g
+            inner join src.core_data c
+            on g.id = c.id;"#;
+
+    let res = execute_sql(sql, pool).await?;
+    info!("{} location records transferred to src schema", res);
+    Ok(())
+}
+
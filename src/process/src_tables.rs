@@ -0,0 +1,76 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+pub async fn create_tables(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+    create schema if not exists src;
+
+    drop table if exists src.core_data;
+    create table src.core_data (
+        id                varchar     not null primary key
+      , ror_full_id       varchar     not null
+      , ror_name          varchar     not null
+      , status            int         not null
+      , established       int         null
+      , location          varchar     null
+      , csubdiv_code      varchar     null
+      , country_code      varchar     null
+    );
+
+    drop table if exists src.names;
+    create table src.names (
+        id                varchar     not null
+      , value             varchar     not null
+      , name_type         int         not null
+      , is_ror_name       bool        not null
+      , lang_code         varchar     null
+      , lang_source       varchar     null
+      , script_code       varchar     null
+      , secondary_script  varchar     null
+    );
+    create index src_names_idx on src.names(id);
+
+    drop table if exists src.relationships;
+    create table src.relationships (
+        id                varchar     not null
+      , ror_name          varchar     not null
+      , rel_type          int         not null
+      , related_id        varchar     not null
+      , related_name      varchar     not null
+    );
+    create index src_relationships_idx on src.relationships(id);
+
+    drop table if exists src.type;
+    create table src.type (
+        id                varchar     not null
+      , ror_name          varchar     not null
+      , org_type          int         not null
+    );
+    create index src_type_idx on src.type(id);
+
+    drop table if exists src.locations;
+    create table src.locations (
+        id                varchar     not null
+      , ror_name          varchar     not null
+      , geonames_id       int         null
+      , location          varchar     null
+      , lat               real        null
+      , lng               real        null
+      , cont_code         varchar     null
+      , cont_name         varchar     null
+      , country_code      varchar     null
+      , country_name      varchar     null
+      , csubdiv_code      varchar     null
+      , csubdiv_name      varchar     null
+    );
+    create index src_locations_idx on src.locations(id);
+
+    SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    Ok(())
+}
@@ -26,6 +26,9 @@ pub struct Flags {
     pub process_data: bool,
     pub export_text: bool,
     pub additional_processing: bool,
+    pub dry_run: bool,
+    pub evaluate: bool,
+    pub export_uncoded: bool,
     pub export_csv: bool,
     pub export_full_csv: bool,
     pub create_config: bool,
@@ -56,6 +59,9 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError>
     let mut p_flag = parse_result.get_flag("p_flag");
     let mut t_flag = parse_result.get_flag("t_flag");
     let mut q_flag = parse_result.get_flag("q_flag");
+    let mut n_flag = parse_result.get_flag("n_flag");
+    let mut e_flag = parse_result.get_flag("e_flag");
+    let mut u_flag = parse_result.get_flag("u_flag");
     let mut x_flag = parse_result.get_flag("x_flag");
     let mut y_flag = parse_result.get_flag("y_flag");
     let c_flag = parse_result.get_flag("c_flag");
@@ -72,6 +78,9 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError>
         p_flag = false;
         t_flag = false;
         q_flag = false;
+        n_flag = false;
+        e_flag = false;
+        u_flag = false;
         x_flag = false;
         y_flag = false;
         z_flag = false;        
@@ -79,6 +88,12 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError>
     
     else {
 
+        // A dry run is always of the additional processing.
+
+        if n_flag {
+            q_flag = true;
+        }
+
         if a_flag  // 'a' (do all) flag set
         {
             r_flag = true;  
@@ -87,11 +102,10 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError>
         }
         else 
         {
-            // if none of r, p, q, t, x or y flags set
+            // if none of r, p, q, t, e, u, x or y flags set
             // set r to be true, as the default with no flags
 
-            if r_flag == false && p_flag == false && t_flag == false
-                && q_flag == false && x_flag == false && y_flag == false {
+            if !(r_flag || p_flag || t_flag || q_flag || e_flag || u_flag || x_flag || y_flag) {
                 r_flag = true;  
             }
         }
@@ -101,6 +115,9 @@ pub fn fetch_valid_arguments(args: Vec<OsString>) -> Result<CliPars, AppError>
         import_ror: r_flag,
         process_data: p_flag,
         additional_processing: q_flag,
+        dry_run: n_flag,
+        evaluate: e_flag,
+        export_uncoded: u_flag,
         export_text: t_flag,
         export_csv: x_flag,
         export_full_csv: y_flag,
@@ -137,6 +154,9 @@ pub fn get_initalising_cli_pars()  -> CliPars {
         import_ror: false,
         process_data: false,
         additional_processing: false,
+        dry_run: false,
+        evaluate: false,
+        export_uncoded: false,
         export_text: false,
         export_csv: false,
         export_full_csv: false,
@@ -159,13 +179,13 @@ pub fn get_initalising_cli_pars()  -> CliPars {
 fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
 
     command!()
-        .about("Imports data from ROR json file (v2) and imports it into a database")
+        .about("Imports data from ROR json file (v1 or v2) and imports it into a database")
         .arg(
              Arg::new("src_file")
             .short('s')
             .long("source")
             .visible_aliases(["source file"])
-            .help("A string with the source file name (over-rides environment setting")
+            .help("A string with the source file name, either a json or csv file or a ROR release zip (over-rides environment setting)")
             .default_value("")
         )
         .arg(
@@ -216,6 +236,30 @@ fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
             .help("Additional processing - apply language codes and add relationship records")
             .action(clap::ArgAction::SetTrue)
         )
+        .arg(
+             Arg::new("n_flag")
+            .short('n')
+            .long("dryrun")
+            .required(false)
+            .help("A flag signifying a dry run of the additional processing, reporting the language codes that would be applied without storing them")
+            .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+             Arg::new("e_flag")
+            .short('e')
+            .long("evaluate")
+            .required(false)
+            .help("A flag signifying an evaluation of the language coding heuristics against the codes supplied by ROR")
+            .action(clap::ArgAction::SetTrue)
+        )
+        .arg(
+             Arg::new("u_flag")
+            .short('u')
+            .long("uncoded")
+            .required(false)
+            .help("A flag signifying output the names still without a language code to a csv file, for manual coding")
+            .action(clap::ArgAction::SetTrue)
+        )
         .arg(
             Arg::new("t_flag")
            .short('t')
@@ -269,7 +313,7 @@ fn parse_args(args: Vec<OsString>) -> Result<ArgMatches, clap::Error> {
             .short('z')
             .long("test")
             .required(false)
-            .help("A flag signifying that this is part of an integration test run - suppresses logs")
+            .help("A flag signifying that this is part of an integration test run, using a temporary test database")
             .action(clap::ArgAction::SetTrue)
        )
        .arg(
@@ -485,6 +529,49 @@ mod tests {
     }
 
 
+    #[test]
+    fn check_cli_with_n_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-n"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert!(!res.flags.import_ror);
+        assert!(!res.flags.process_data);
+        assert!(res.flags.additional_processing);
+        assert!(res.flags.dry_run);
+        assert!(!res.flags.export_text);
+        assert!(!res.flags.test_run);
+    }
+
+
+    #[test]
+    fn check_cli_with_e_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-e"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert!(!res.flags.import_ror);
+        assert!(!res.flags.additional_processing);
+        assert!(!res.flags.dry_run);
+        assert!(res.flags.evaluate);
+    }
+
+
+    #[test]
+    fn check_cli_with_u_flag() {
+        let target = "dummy target";
+        let args : Vec<&str> = vec![target, "-q", "-u"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+
+        let res = fetch_valid_arguments(test_args).unwrap();
+        assert!(!res.flags.import_ror);
+        assert!(res.flags.additional_processing);
+        assert!(res.flags.export_uncoded);
+    }
+
+
     #[test]
     fn check_cli_with_string_pars() {
         let target = "dummy target";
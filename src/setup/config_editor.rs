@@ -94,6 +94,10 @@ pub fn edit_config_file() -> Result<(), AppError>
     let db_name = format!("db_name=\"{}\"", dname);
     println!("{}", db_name);
 
+    // The maintenance database name is not edited here, but is carried over.
+
+    let admin_db_name = format!("admin_db_name=\"{}\"", current_config.db_pars.admin_db_name);
+
 
     let p1 = "Section 2: FOLDERS";
     let p2 = "DATA FOLDER";
@@ -230,7 +234,7 @@ pub fn edit_config_file() -> Result<(), AppError>
     }
 
 
-    let database_section = format!("[database]\n{}\n{}\n{}\n{}\n{}\n", db_host, db_user, db_password, db_port, db_name);
+    let database_section = format!("[database]\n{}\n{}\n{}\n{}\n{}\n{}\n", db_host, db_user, db_password, db_port, db_name, admin_db_name);
     let folders_section = format!("[folders]\n{}\n{}\n{}\n", data_folder_path, output_folder_path, log_folder_path);
     let data_section = format!("[data]\n{}\n{}\n{}\n", src_file_name, data_version, data_date);
     let config_string = format!("\n{}\n\n{}\n\n{}\n", data_section, folders_section, database_section);
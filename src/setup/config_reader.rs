@@ -34,6 +34,7 @@ pub struct TomlDBPars {
     pub db_password: Option<String>,
     pub db_port: Option<String>,
     pub db_name: Option<String>,
+    pub admin_db_name: Option<String>,
 }
 
 
@@ -62,6 +63,7 @@ pub struct DBPars {
     pub db_password: String,
     pub db_port: usize,
     pub db_name: String,
+    pub admin_db_name: String,
 }
 
 pub static DB_PARS: OnceLock<DBPars> = OnceLock::new();
@@ -166,12 +168,22 @@ fn verify_db_parameters(toml_database: TomlDBPars) -> Result<DBPars, AppError> {
 
     let db_name = check_defaulted_string (toml_database.db_name, "DB name", "ror", "ror");
 
+    // The server's maintenance database is only used to create and drop the
+    // databases for test runs, and is almost always 'postgres', so a missing
+    // value is not reported.
+
+    let admin_db_name = match toml_database.admin_db_name {
+        Some(s) if s.trim() != "" => s,
+        _ => "postgres".to_string(),
+    };
+
     Ok(DBPars {
         db_host,
         db_user,
         db_password,
         db_port,
         db_name,
+        admin_db_name,
     })
 }
 
@@ -223,7 +235,17 @@ pub fn fetch_db_name() -> Result<String, AppError> {
     Ok(db_pars.db_name.clone())
 }
 
-pub fn fetch_db_conn_string(db_name: &String) -> Result<String, AppError> {
+pub fn fetch_admin_db_name() -> Result<String, AppError> {
+    let db_pars = match DB_PARS.get() {
+         Some(dbp) => dbp,
+         None => {
+            return Result::Err(AppError::MissingDBParameters());
+        },
+    };
+    Ok(db_pars.admin_db_name.clone())
+}
+
+pub fn fetch_db_conn_string(db_name: &str) -> Result<String, AppError> {
     let db_pars = match DB_PARS.get() {
          Some(dbp) => dbp,
          None => {
@@ -263,6 +285,7 @@ db_user="user_name"
 db_password="password"
 db_port="5432"
 db_name="ror"
+admin_db_name="maintenance"
 "#;
         let config_string = config.to_string();
         let res = populate_config_vars(&config_string).unwrap();
@@ -279,6 +302,7 @@ db_name="ror"
         assert_eq!(res.db_pars.db_password, "password");
         assert_eq!(res.db_pars.db_port, 5432);
         assert_eq!(res.db_pars.db_name, "ror");
+        assert_eq!(res.db_pars.admin_db_name, "maintenance");
     }
     
 
@@ -461,6 +485,7 @@ db_password="password"
         assert_eq!(res.db_pars.db_password, "password");
         assert_eq!(res.db_pars.db_port, 5432);
         assert_eq!(res.db_pars.db_name, "ror");
+        assert_eq!(res.db_pars.admin_db_name, "postgres");
     }
 
 
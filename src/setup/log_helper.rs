@@ -6,7 +6,7 @@
  ***************************************************************************/
 
 use chrono::Local;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::err::AppError;
 use crate::setup::InitParams;
 
@@ -33,7 +33,10 @@ fn get_log_file_path(data_folder: &PathBuf, source_file_name : &String) -> PathB
     let datetime_string = Local::now().format("%m-%d %H%M%S").to_string();
     let mut log_file_name = format!("ror {} ", datetime_string);
     if source_file_name != "" {
-        let source_file = &source_file_name[..(source_file_name.len() - 5)];
+        let source_file = match Path::new(source_file_name).file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => source_file_name.to_string(),
+        };
         log_file_name = format!("{} from {}.log", log_file_name, source_file);
     }
     else {
@@ -99,6 +102,10 @@ pub fn log_startup_params (ip : &InitParams) {
     info!("create summary tables: {}", ip.flags.create_summary);
     info!("import_ror: {}", ip.flags.import_ror);
     info!("process_data: {}", ip.flags.process_data);
+    info!("additional_processing: {}", ip.flags.additional_processing);
+    info!("dry_run: {}", ip.flags.dry_run);
+    info!("evaluate: {}", ip.flags.evaluate);
+    info!("export_uncoded: {}", ip.flags.export_uncoded);
     info!("export_text: {}", ip.flags.export_text);
     info!("export_csv: {}", ip.flags.export_csv);
     info!("export_all_csv: {}", ip.flags.export_full_csv);
@@ -9,7 +9,10 @@ The module also checks the parameters for completeness (those required will vary
 depending on the activity specified). If possible, defaults are used to stand in for 
 mising parameters. If not possible the program stops with a message explaining the 
 problem.
-The module also provides a database connection pool on demand.
+The module also provides a database connection pool on demand. For test runs
+(-z) the pool is for a separate, temporary test database, named from the test
+folder, so that test data never reaches the production schemas. That database 
+is created at the start of the test run and dropped at its end.
 ***********************************************************************************/
 
 pub mod cli_reader;
@@ -26,7 +29,7 @@ use chrono::NaiveDate;
 use sqlx::postgres::{PgPoolOptions, PgConnectOptions, PgPool};
 use sqlx::{Postgres, Pool};
 use log::{info, error};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::time::Duration;
 use regex::Regex;
@@ -59,6 +62,9 @@ pub fn get_params(cli_pars: CliPars, config_string: &String) -> Result<InitParam
     let mut data_folder_good = true;
 
     if cli_pars.flags.test_run {
+        if cli_pars.test_folder == empty_pb {
+            return Result::Err(AppError::MissingProgramParameter("test_folder".to_string()));
+        }
         data_folder  =  cli_pars.test_folder;
     }
     else {
@@ -83,8 +89,10 @@ pub fn get_params(cli_pars: CliPars, config_string: &String) -> Result<InitParam
         }
     }
 
+    // Test runs write their outputs to the test folder rather than the usual outputs folder.
+
     let mut output_folder = folder_pars.output_folder_path;
-    if output_folder == empty_pb && data_folder_good {
+    if flags.test_run || (output_folder == empty_pb && data_folder_good) {
         output_folder = data_folder.clone();
     }
     else {
@@ -103,14 +111,11 @@ pub fn get_params(cli_pars: CliPars, config_string: &String) -> Result<InitParam
         }
     }
 
-    // Also ensure source file name ends in '.json', if it doesn't already.
+    // Also ensure source file name has a recognised extension ('.json', or '.zip' 
+    // for a ROR release archive). If it doesn't '.json' is assumed and added.
 
-    let name_len = source_file_name.len();
-    if name_len > 5 {
-        let ext = &source_file_name[(name_len - 5)..];
-        if ext != ".json" {
-            source_file_name = source_file_name + ".json";
-       }
+    if !source_file_name.is_empty() && !has_source_file_extension(&source_file_name) {
+        source_file_name = source_file_name + ".json";
     }
         
     let mut data_version = "".to_string();
@@ -177,6 +182,26 @@ pub fn get_params(cli_pars: CliPars, config_string: &String) -> Result<InitParam
 }
 
 
+// Processing and export work on the data most recently imported into the ror
+// schema. If no version has been specified that version is used. If one has
+// been it must match the stored version.
+
+pub fn check_data_version(data_version: &str, stored: Option<(String, String)>) -> Result<(String, String), AppError> {
+
+    match stored {
+        None => Err(AppError::MissingVersion(data_version.to_string())),
+        Some((stored_version, stored_date)) => {
+            if !data_version.is_empty() && data_version != stored_version {
+                Err(AppError::IncompatibleVersions(data_version.to_string(), stored_version))
+            }
+            else {
+                Ok((stored_version, stored_date))
+            }
+        },
+    }
+}
+
+
 fn folder_exists(folder_name: &PathBuf) -> bool {
     let res = match folder_name.try_exists() {
         Ok(true) => true,
@@ -191,16 +216,88 @@ pub async fn get_db_pool() -> Result<PgPool, AppError> {
 
     // Establish DB name and thence the connection string
     // (done as two separate steps to allow for future development).
-    // Use the string to set up a connection options object and change 
-    // the time threshold for warnings. Set up a DB pool option and 
-    // connect using the connection options object.
 
     let db_name = match config_reader::fetch_db_name() {
         Ok(n) => n,
         Err(e) => return Err(e),
     };
+    connect_to_db(&db_name).await
+}
+
+
+pub async fn get_test_db_pool(test_folder: &Path) -> Result<PgPool, AppError> {  
+
+    // The test database is created, if necessary, from the server's 
+    // maintenance database (the admin_db_name in the config file, normally
+    // 'postgres'), and then connected to in the normal way.
+
+    let test_db = get_test_db_name(&config_reader::fetch_db_name()?, test_folder);
+    let admin_pool = connect_to_db(&config_reader::fetch_admin_db_name()?).await?;
+
+    let sql = "select exists (select 1 from pg_database where datname = $1);";
+    let db_exists: bool = sqlx::query_scalar(sql).bind(&test_db).fetch_one(&admin_pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    if !db_exists {
+        let sql = format!(r#"create database "{}" encoding 'UTF8' template template0;"#, test_db);
+        sqlx::raw_sql(&sql).execute(&admin_pool).await
+            .map_err(|e| AppError::SqlxError(e, sql))?;
+    }
+    admin_pool.close().await;
+
+    info!("Test run using database {}", test_db);
+    connect_to_db(&test_db).await
+}
+
+
+pub async fn drop_test_db(pool: PgPool, test_folder: &Path) -> Result<(), AppError> {  
+
+    // All connections to the test database must be closed before it can be dropped.
 
-    let db_conn_string = config_reader::fetch_db_conn_string(&db_name)?;  
+    pool.close().await;
+    let test_db = get_test_db_name(&config_reader::fetch_db_name()?, test_folder);
+    let admin_pool = connect_to_db(&config_reader::fetch_admin_db_name()?).await?;
+
+    let sql = format!(r#"drop database if exists "{}" with (force);"#, test_db);
+    sqlx::raw_sql(&sql).execute(&admin_pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))?;
+    admin_pool.close().await;
+
+    info!("Test database {} dropped", test_db);
+    Ok(())
+}
+
+
+// The test database name combines the production database name with the 
+// name of the test folder, so that separate test runs, e.g. of different 
+// integration tests, each have their own database. It is always different 
+// from the production database name.
+
+fn get_test_db_name(db_name: &str, test_folder: &Path) -> String {
+
+    let folder_name = test_folder.file_name()
+        .map(|f| f.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let folder_part: String = folder_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let folder_part = folder_part.trim_matches('_');
+
+    if folder_part.is_empty() {
+        format!("{}_test", db_name)
+    }
+    else {
+        format!("{}_test_{}", db_name, folder_part)
+    }
+}
+
+
+async fn connect_to_db(db_name: &str) -> Result<PgPool, AppError> {  
+
+    // Use the connection string to set up a connection options object and 
+    // change the time threshold for warnings. Set up a DB pool option and 
+    // connect using the connection options object.
+
+    let db_conn_string = config_reader::fetch_db_conn_string(db_name)?;  
    
     let mut opts: PgConnectOptions = db_conn_string.parse()
                     .map_err(|e| AppError::DBPoolError("Problem with parsing conection string".to_string(), e))?;
@@ -281,6 +378,11 @@ pub async fn create_lup_tables(pool : &Pool<Postgres>) -> Result<(), AppError>
 }
 
 
+fn has_source_file_extension(file_name: &str) -> bool {
+    let lc_name = file_name.to_lowercase();
+    lc_name.ends_with(".json") || lc_name.ends_with(".zip") || lc_name.ends_with(".csv")
+}
+
 fn is_compliant_file_name(input: &String) -> bool {
     let file_name_pattern = r#"^v[0-9]+(\.[0-9]+){0,2}(-| )20[0-9]{2}-?[01][0-9]-?[0-3][0-9]"#;
     let re = Regex::new(file_name_pattern).unwrap();
@@ -375,6 +477,31 @@ mod tests {
       assert_eq!(get_data_date(&test_file_name), "2025-01-23");
    }
    
+   #[test]
+   fn check_file_name_regex_works_with_zip () {
+      let test_file_name = "v1.59-2025-01-23-ror-data.zip".to_string();
+      assert!(is_compliant_file_name(&test_file_name));
+      assert_eq!(get_data_version(&test_file_name), "v1.59");
+      assert_eq!(get_data_date(&test_file_name), "2025-01-23");
+   }
+
+   #[test]
+   fn check_source_file_extensions () {
+      assert!(has_source_file_extension("v1.59-2025-01-23-ror-data_schema_v2.json"));
+      assert!(has_source_file_extension("v1.59-2025-01-23-ror-data.zip"));
+      assert!(has_source_file_extension("v1.59-2025-01-23-ror-data.ZIP"));
+      assert!(has_source_file_extension("v1.59-2025-01-23-ror-data_schema_v2.csv"));
+      assert!(!has_source_file_extension("v1.58 20241211"));
+      assert!(!has_source_file_extension("schema2.1 data"));
+   }
+
+   #[test]
+   fn check_test_db_names () {
+      assert_eq!(get_test_db_name("ror", Path::new("./tests/v2 basic-import")), "ror_test_v2_basic_import");
+      assert_eq!(get_test_db_name("ror", Path::new("/home/data/Test_Data")), "ror_test_test_data");
+      assert_eq!(get_test_db_name("ror", Path::new("")), "ror_test");
+   }
+
    #[test]
     fn check_file_name_regex_works_7 () {
         let test_file_name = "1.50 2024-12-11.json".to_string();
@@ -625,6 +752,45 @@ db_name="ror"
         assert_eq!(res.data_date, "2024-12-11");
     }
  
+    #[test]
+    fn check_zip_source_file_name_is_not_extended() {
+
+        let config = r#"
+[data]
+src_file_name="v1.59-2025-01-23-ror-data.zip"
+
+[folders]
+data_folder_path="/home/steve/Data/MDR source data/ROR/data"
+output_folder_path="/home/steve/Data/MDR source data/ROR/outputs"
+log_folder_path="/home/steve/Data/MDR/MDR_Logs/ror"
+
+[database]
+db_host="localhost"
+db_user="user_name"
+db_password="password"
+db_port="5433"
+db_name="ror"
+"#;
+        let config_string = config.to_string();
+        config_reader::populate_config_vars(&config_string).unwrap();
+
+        let args : Vec<&str> = vec!["dummy target", "-p"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let cli_pars = cli_reader::fetch_valid_arguments(test_args).unwrap();
+        let res = get_params(cli_pars, &config_string).unwrap();
+
+        assert_eq!(res.source_file_name, "v1.59-2025-01-23-ror-data.zip");
+        assert_eq!(res.data_version, "v1.59");
+        assert_eq!(res.data_date, "2025-01-23");
+
+        let args : Vec<&str> = vec!["dummy target", "-p", "-s", "v1.59-2025-01-23-ror-data"];
+        let test_args = args.iter().map(|x| x.to_string().into()).collect::<Vec<OsString>>();
+        let cli_pars = cli_reader::fetch_valid_arguments(test_args).unwrap();
+        let res = get_params(cli_pars, &config_string).unwrap();
+
+        assert_eq!(res.source_file_name, "v1.59-2025-01-23-ror-data.json");
+    }
+
     #[test]
     #[should_panic]
     fn check_wrong_data_folder_panics_if_r() {
@@ -702,5 +868,20 @@ db_name="ror"
         assert_eq!(res.data_date, "2024-12-11");
     }
 
-}
 
+    #[test]
+    fn check_data_version_defaults_to_stored_version() {
+        let stored = Some(("v1.59".to_string(), "2025-01-23".to_string()));
+        let res = check_data_version("", stored).unwrap();
+        assert_eq!(res, ("v1.59".to_string(), "2025-01-23".to_string()));
+    }
+
+    #[test]
+    fn check_data_version_must_match_stored_version() {
+        let stored = Some(("v1.59".to_string(), "2025-01-23".to_string()));
+        assert!(check_data_version("v1.59", stored.clone()).is_ok());
+        assert!(matches!(check_data_version("v1.58", stored), Err(AppError::IncompatibleVersions(_, _))));
+        assert!(matches!(check_data_version("", None), Err(AppError::MissingVersion(_))));
+    }
+
+}
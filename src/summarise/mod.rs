@@ -0,0 +1,52 @@
+/***************************************************************************
+ * The summarise module holds, in the smm schema, a summary of each version
+ * of the data - a row per version with the main totals, and the counts of
+ * orgs by status, type, country and continent, and of names by name type,
+ * language, script and language source, and the number of names without
+ * a language code in each country. The process step (-p) summarises
+ * the src data, and the additional processing step (-q) then replaces the
+ * name counts with those from the ext tables, so that the effect of the
+ * language coding can be tracked from one release to the next.
+ * The smm tables are only recreated when explicitly requested (-m), as
+ * they accumulate the summaries of successive versions.
+ ***************************************************************************/
+
+mod smm_helper;
+mod smm_tables;
+
+use log::{info, warn, error};
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+pub async fn create_smm_tables(pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    match smm_tables::create_tables(pool).await {
+        Ok(()) => info!("Tables created for smm schema"),
+        Err(e) => {
+            error!("An error occured while creating the smm schema tables: {}", e);
+            return Err(e)
+            },
+    };
+    Ok(())
+}
+
+
+pub async fn summarise_data(data_version: &str, data_date: &str, names_schema: &str, 
+                            pool : &Pool<Postgres>) -> Result<(), AppError>
+{
+    if !smm_helper::smm_tables_exist(pool).await? {
+        warn!("Summary tables not found - run with -m to create them before the data can be summarised");
+        return Ok(());
+    }
+
+    // Any existing summary of this version is replaced.
+
+    smm_helper::delete_any_existing_data(data_version, pool).await?;
+    smm_helper::store_version_summary(data_version, data_date, names_schema, pool).await?;
+    smm_helper::store_org_attributes(data_version, pool).await?;
+    smm_helper::store_name_attributes(data_version, names_schema, pool).await?;
+
+    info!("Version {} summarised, using names from the {} schema", data_version, names_schema);
+    Ok(())
+}
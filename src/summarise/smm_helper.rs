@@ -0,0 +1,134 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+use log::info;
+
+
+// The org attributes are always taken from the src tables. The name attributes
+// are taken from the src or the ext tables, depending on the processing stage.
+
+const ORG_ATTRIBUTES: [(&str, &str); 4] = [
+    ("org_status", r#"select c.status::varchar as code, s.name as name, count(*) as number
+            from src.core_data c
+            left join lup.ror_status_types s on c.status = s.id
+            group by c.status, s.name"#),
+    ("org_type", r#"select t.org_type::varchar as code, ot.name as name, count(distinct t.id) as number
+            from src.type t
+            left join lup.ror_org_types ot on t.org_type = ot.id
+            group by t.org_type, ot.name"#),
+    ("org_country", r#"select l.country_code as code, coalesce(lc.name, min(l.country_name)) as name, 
+            count(distinct l.id) as number
+            from src.locations l
+            left join lup.countries lc on l.country_code = lc.code
+            group by l.country_code, lc.name"#),
+    ("org_continent", r#"select cont_code as code, min(cont_name) as name, count(distinct id) as number
+            from src.locations
+            group by cont_code"#),
+];
+
+const NAME_ATTRIBUTES: [(&str, &str); 5] = [
+    ("name_type", r#"select n.name_type::varchar as code, t.name as name, count(*) as number
+            from {schema}.names n
+            left join lup.ror_name_types t on n.name_type = t.id
+            group by n.name_type, t.name"#),
+    ("name_lang", r#"select n.lang_code as code, c.name as name, count(*) as number
+            from {schema}.names n
+            left join lup.lang_codes c on n.lang_code = c.code
+            group by n.lang_code, c.name"#),
+    ("name_script", r#"select n.script_code as code, s.unicode_name as name, count(*) as number
+            from {schema}.names n
+            left join lup.lang_scripts s on n.script_code = s.code
+            group by n.script_code, s.unicode_name"#),
+    ("name_lang_source", r#"select lang_source as code, lang_source as name, count(*) as number
+            from {schema}.names
+            group by lang_source"#),
+    ("name_nolc_country", r#"select c.country_code as code, lc.name as name, count(*) as number
+            from {schema}.names n
+            inner join src.core_data c on n.id = c.id
+            left join lup.countries lc on c.country_code = lc.code
+            where n.lang_code is null
+            group by c.country_code, lc.name"#),
+];
+
+
+pub async fn smm_tables_exist(pool: &Pool<Postgres>) -> Result<bool, AppError> {
+
+    let sql = "select to_regclass('smm.version_summary') is not null";
+    sqlx::query_scalar(sql).fetch_one(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))
+}
+
+
+pub async fn delete_any_existing_data(data_version: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    // Will do nothing if the smm tables have not yet been created.
+
+    if !smm_tables_exist(pool).await? {
+        return Ok(());
+    }
+
+    for sql in ["delete from smm.version_summary where data_version = $1;",
+                "delete from smm.attributes_summary where data_version = $1;"] {
+        sqlx::query(sql).bind(data_version).execute(pool).await
+            .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+    }
+    Ok(())
+}
+
+
+pub async fn store_version_summary(data_version: &str, data_date: &str, names_schema: &str,
+                                   pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = format!(r#"insert into smm.version_summary (data_version, data_date, 
+            num_orgs, num_names, num_names_lc, num_names_nolc, names_stage)
+            select $1, $2::date, 
+            (select count(*) from src.core_data),
+            count(*), count(lang_code), count(*) - count(lang_code), $3
+            from {}.names;"#, names_schema);
+
+    sqlx::query(&sql).bind(data_version).bind(data_date).bind(names_schema)
+        .execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))?;
+    Ok(())
+}
+
+
+pub async fn store_org_attributes(data_version: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    for (att_type, att_sql) in ORG_ATTRIBUTES {
+        let res = store_attribute(data_version, att_type, att_sql, "src.core_data", pool).await?;
+        info!("{} {} categories summarised", res, att_type);
+    }
+    Ok(())
+}
+
+
+pub async fn store_name_attributes(data_version: &str, names_schema: &str, pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let names_table = format!("{}.names", names_schema);
+    for (att_type, att_sql) in NAME_ATTRIBUTES {
+        let att_sql = att_sql.replace("{schema}", names_schema);
+        let res = store_attribute(data_version, att_type, &att_sql, &names_table, pool).await?;
+        info!("{} {} categories summarised", res, att_type);
+    }
+    Ok(())
+}
+
+
+async fn store_attribute(data_version: &str, att_type: &str, att_sql: &str, total_table: &str,
+                         pool: &Pool<Postgres>) -> Result<u64, AppError> {
+
+    // Percentages are of the total number of orgs or names, so those for
+    // attributes that an org can have more than once (e.g. type) may sum to more than 100.
+
+    let sql = format!(r#"insert into smm.attributes_summary (data_version, att_type, 
+            att_code, att_name, number, pc)
+            select $1, $2, a.code, coalesce(a.name, a.code, 'none'), a.number,
+            round(100.0 * a.number / (select greatest(count(*), 1) from {}), 2)
+            from ({}) a
+            order by a.number desc;"#, total_table, att_sql);
+
+    let res = sqlx::query(&sql).bind(data_version).bind(att_type)
+        .execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql))?;
+    Ok(res.rows_affected())
+}
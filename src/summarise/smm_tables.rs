@@ -0,0 +1,39 @@
+use sqlx::{Pool, Postgres};
+use crate::AppError;
+
+
+pub async fn create_tables(pool: &Pool<Postgres>) -> Result<(), AppError> {
+
+    let sql = r#"SET client_min_messages TO WARNING;
+    create schema if not exists smm;
+
+    drop table if exists smm.version_summary;
+    create table smm.version_summary (
+        data_version      varchar     not null primary key
+      , data_date         date        not null
+      , num_orgs          int         not null
+      , num_names         int         not null
+      , num_names_lc      int         not null
+      , num_names_nolc    int         not null
+      , names_stage       varchar     not null
+      , summarised        timestamp   not null default now()
+    );
+
+    drop table if exists smm.attributes_summary;
+    create table smm.attributes_summary (
+        data_version      varchar     not null
+      , att_type          varchar     not null
+      , att_code          varchar     null
+      , att_name          varchar     null
+      , number            int         not null
+      , pc                real        not null
+    );
+    create index attributes_summary_idx on smm.attributes_summary(data_version, att_type);
+
+    SET client_min_messages TO NOTICE;"#;
+
+    sqlx::raw_sql(sql).execute(pool).await
+        .map_err(|e| AppError::SqlxError(e, sql.to_string()))?;
+
+    Ok(())
+}